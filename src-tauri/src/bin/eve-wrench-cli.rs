@@ -0,0 +1,3 @@
+fn main() {
+    eve_wrench_lib::run_cli()
+}
@@ -0,0 +1,243 @@
+use crate::error::{AppError, AppResult, IoResultExt};
+use crate::evesettings::{
+    analyze_import_archive, apply_aliases, backup_settings_file, copy_settings_files,
+    export_settings_archive, import_archive, load_aliases, scan_backups, scan_installations,
+    BackupEntry, ProfileData, Server, TargetResult,
+};
+use crate::location;
+use crate::prefs::{get_prefs, set_pref_value};
+use crate::restore::{restore_backup_file, RestoreMtime};
+use clap::{Parser, Subcommand};
+use serde::Serialize;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+// Must match `identifier` in tauri.conf.json, so the CLI shares aliases, the operation
+// journal and the backup folder setting with the app.
+const APP_IDENTIFIER: &str = "com.timkunze.eve-wrench";
+
+#[derive(Parser)]
+#[command(
+    name = "eve-wrench-cli",
+    version,
+    about = "Back up, restore and copy EVE Online settings. Results are printed as JSON."
+)]
+struct Cli {
+    /// EVE settings folder, when it is not in the default location
+    #[arg(long, global = true)]
+    eve_path: Option<String>,
+
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// List profiles, settings files and backups of every server
+    List,
+    /// Back up a settings file
+    Backup {
+        /// core_user_*.dat or core_char_*.dat file
+        path: String,
+        #[arg(long, default_value = "cli")]
+        name: String,
+    },
+    /// Restore a backup onto the file it was taken from, or another one
+    Restore {
+        backup: String,
+        /// Settings file to restore onto
+        #[arg(long, conflicts_with = "target_id")]
+        target: Option<String>,
+        /// Account or character id in the backup's profile to restore onto
+        #[arg(long)]
+        target_id: Option<String>,
+        /// Modification time of the restored file: now, backup or keep
+        #[arg(long)]
+        mtime: Option<String>,
+    },
+    /// Copy a settings file or backup over other settings files. Exits non-zero when any
+    /// target could not be written
+    Copy {
+        source: String,
+        #[arg(required = true)]
+        targets: Vec<String>,
+    },
+    /// Export settings, backups and aliases to a zip archive
+    Export { path: String },
+    /// Import an archive made by export. Exits non-zero when any file could not be
+    /// imported
+    Import {
+        path: String,
+        /// Only report what the import would do
+        #[arg(long)]
+        analyze: bool,
+        /// Overwrite local files that differ from the archive
+        #[arg(long)]
+        overwrite: bool,
+    },
+    /// Read or change prefs.ini preferences
+    Prefs {
+        #[command(subcommand)]
+        command: PrefsCommand,
+    },
+}
+
+#[derive(Subcommand)]
+enum PrefsCommand {
+    /// Show the preferences of every profile of a server
+    Get { server_path: String },
+    /// Set a preference, or remove it when no value is given
+    Set {
+        server_path: String,
+        name: String,
+        value: Option<String>,
+        /// Only change this profile folder instead of all of them
+        #[arg(long)]
+        profile: Option<String>,
+    },
+}
+
+#[derive(Serialize)]
+struct ServerListing {
+    server: Server,
+    path: String,
+    profiles: Vec<ProfileData>,
+}
+
+#[derive(Serialize)]
+struct Listing {
+    servers: Vec<ServerListing>,
+    backups: Vec<BackupEntry>,
+}
+
+fn data_dir() -> AppResult<PathBuf> {
+    let path = dirs::data_dir()
+        .ok_or_else(|| AppError::not_found("Could not determine the data directory"))?
+        .join(APP_IDENTIFIER);
+    fs::create_dir_all(&path).at("create", &path)?;
+    Ok(path)
+}
+
+fn to_json<T: Serialize>(value: &T) -> AppResult<String> {
+    serde_json::to_string_pretty(value).map_err(|e| AppError::other(e.to_string()))
+}
+
+/// Output for a write to several targets. When any target failed, the full result is
+/// still printed and the command fails with the first target's error, so scripts can
+/// tell a partial write from a complete one by the exit code.
+fn report_targets<T: Serialize>(output: &T, targets: &[TargetResult]) -> AppResult<String> {
+    let json = to_json(output)?;
+    match targets.iter().find_map(|t| t.error.clone()) {
+        Some(error) => {
+            println!("{}", json);
+            Err(error)
+        }
+        None => Ok(json),
+    }
+}
+
+fn list(data_dir: &Path, eve_path: Option<&str>) -> AppResult<Listing> {
+    let (mut server_profiles, server_paths) = scan_installations(eve_path)?;
+    apply_aliases(&mut server_profiles, &load_aliases(data_dir));
+
+    let servers = Server::ALL
+        .into_iter()
+        .filter_map(|server| {
+            let profiles = server_profiles.remove(&server)?;
+            let path = server_paths.get(&server).cloned().unwrap_or_default();
+            Some(ServerListing {
+                server,
+                path: path.to_string_lossy().into_owned(),
+                profiles,
+            })
+        })
+        .collect();
+
+    Ok(Listing {
+        servers,
+        backups: scan_backups(eve_path)?,
+    })
+}
+
+fn execute(cli: Cli) -> AppResult<String> {
+    let data_dir = data_dir()?;
+    location::init(&data_dir);
+    let eve_path = cli.eve_path;
+
+    match cli.command {
+        Command::List => to_json(&list(&data_dir, eve_path.as_deref())?),
+        Command::Backup { path, name } => {
+            to_json(&backup_settings_file(Path::new(&path), &name, None)?)
+        }
+        Command::Restore {
+            backup,
+            target,
+            target_id,
+            mtime,
+        } => {
+            let mtime = mtime
+                .map(|m| {
+                    serde_json::from_value::<RestoreMtime>(serde_json::Value::String(m)).map_err(
+                        |_| AppError::invalid_input("mtime must be one of: now, backup, keep"),
+                    )
+                })
+                .transpose()?;
+            to_json(&restore_backup_file(
+                &data_dir, backup, target, target_id, mtime,
+            )?)
+        }
+        Command::Copy { source, targets } => {
+            let results = copy_settings_files(&data_dir, source, targets)?;
+            report_targets(&results, &results)
+        }
+        Command::Export { path } => to_json(&export_settings_archive(&data_dir, eve_path, path)?),
+        Command::Import {
+            path,
+            analyze,
+            overwrite,
+        } => {
+            let analysis = analyze_import_archive(&data_dir, path.clone(), eve_path.clone())?;
+            if analyze {
+                return to_json(&analysis);
+            }
+            let overwrite_paths = if overwrite {
+                analysis
+                    .conflicts
+                    .into_iter()
+                    .map(|c| c.relative_path)
+                    .collect()
+            } else {
+                Vec::new()
+            };
+            let result = import_archive(&data_dir, path, eve_path, overwrite_paths)?;
+            report_targets(&result, &result.failed)
+        }
+        Command::Prefs { command } => match command {
+            PrefsCommand::Get { server_path } => to_json(&get_prefs(server_path)?),
+            PrefsCommand::Set {
+                server_path,
+                name,
+                value,
+                profile,
+            } => {
+                set_pref_value(&data_dir, server_path.clone(), profile, name, value)?;
+                to_json(&get_prefs(server_path)?)
+            }
+        },
+    }
+}
+
+/// Entry point of the eve-wrench-cli binary.
+pub fn run() {
+    match execute(Cli::parse()) {
+        Ok(output) => println!("{}", output),
+        Err(e) => {
+            // Same shape the app's commands reject with, so scripts can match on `code`
+            match serde_json::to_string_pretty(&e) {
+                Ok(json) => eprintln!("{}", json),
+                Err(_) => eprintln!("error: {}", e),
+            }
+            std::process::exit(1);
+        }
+    }
+}
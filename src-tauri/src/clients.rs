@@ -0,0 +1,343 @@
+use crate::error::{AppError, AppResult, ErrorCode};
+use crate::evesettings::{
+    app_data_dir, copy_section_files, copy_settings_files, emit_data_changed, import_archive,
+    parse_settings_filename, Server, TargetResult,
+};
+use crate::overview::import_overview_files;
+use crate::shortcuts::import_shortcut_files;
+use crate::windows::{apply_layout_files, ScreenSpec};
+use serde::{Deserialize, Serialize};
+use std::ffi::OsStr;
+use std::fs::OpenOptions;
+use std::path::Path;
+use std::sync::Mutex;
+use std::thread;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use sysinfo::{ProcessRefreshKind, ProcessesToUpdate, System, UpdateKind};
+use tauri::{Emitter, Manager};
+
+const CLIENT_EXE: &str = "exefile.exe";
+const TICK: Duration = Duration::from_secs(5);
+
+/// A running EVE client.
+#[derive(Serialize, Debug, Clone)]
+pub struct RunningClient {
+    pub pid: u32,
+    pub server: Server,
+}
+
+fn is_client_exe(name: &OsStr) -> bool {
+    let name = name.to_string_lossy();
+    // Under Wine/Proton the command line holds a Windows path
+    let file = name.rsplit(['/', '\\']).next().unwrap_or(&name);
+    file.eq_ignore_ascii_case(CLIENT_EXE)
+}
+
+// The launcher passes `/server:<name>` for everything but Tranquility
+fn client_server(cmd: &[std::ffi::OsString]) -> Server {
+    cmd.iter()
+        .filter_map(|arg| {
+            let arg = arg.to_string_lossy();
+            let (flag, value) = arg.split_once(':')?;
+            flag.eq_ignore_ascii_case("/server")
+                .then(|| Server::from_folder_name(value))
+                .flatten()
+        })
+        .next()
+        .unwrap_or(Server::Tranquility)
+}
+
+/// EVE clients currently running, found by their `exefile.exe` process. On Linux the
+/// client runs under Wine/Proton, so the executable shows up in the command line.
+pub(crate) fn running_clients() -> Vec<RunningClient> {
+    let mut system = System::new();
+    system.refresh_processes_specifics(
+        ProcessesToUpdate::All,
+        true,
+        ProcessRefreshKind::nothing()
+            .with_cmd(UpdateKind::OnlyIfNotSet)
+            .with_exe(UpdateKind::OnlyIfNotSet),
+    );
+
+    let mut clients: Vec<RunningClient> = system
+        .processes()
+        .iter()
+        .filter(|(_, process)| {
+            is_client_exe(process.name())
+                || process
+                    .exe()
+                    .is_some_and(|exe| is_client_exe(exe.as_os_str()))
+                || process.cmd().first().is_some_and(|arg| is_client_exe(arg))
+        })
+        .map(|(pid, process)| RunningClient {
+            pid: pid.as_u32(),
+            server: client_server(process.cmd()),
+        })
+        .collect();
+    clients.sort_by_key(|c| c.pid);
+    clients
+}
+
+/// Server whose settings folder contains `path`.
+pub(crate) fn server_of_path(path: &Path) -> Option<Server> {
+    path.ancestors()
+        .filter_map(|dir| dir.file_name()?.to_str())
+        .find_map(Server::from_folder_name)
+}
+
+/// Whether `path` is a file the client rewrites on logout. Backups and sidecars are
+/// never touched by it.
+pub(crate) fn written_by_client(path: &Path) -> bool {
+    path.file_name()
+        .and_then(|n| n.to_str())
+        .is_some_and(|n| parse_settings_filename(n).is_some() || n == "prefs.ini")
+}
+
+fn client_running_error(server: Server, path: &Path) -> AppError {
+    AppError::new(
+        ErrorCode::ClientRunning,
+        format!(
+            "EVE is running on {}; close the client before changing its settings",
+            server.display_name()
+        ),
+    )
+    .with_path(path)
+}
+
+// Another process holding the file open shows up as a sharing violation on Windows
+fn check_unlocked(path: &Path) -> AppResult<()> {
+    match OpenOptions::new().append(true).open(path) {
+        Err(e) if path.exists() => {
+            let error = AppError::io("open", path, e);
+            if error.code == ErrorCode::FileLocked {
+                Err(error)
+            } else {
+                Ok(())
+            }
+        }
+        _ => Ok(()),
+    }
+}
+
+/// Refuses to write `path` while a client of its server is running, since the client
+/// would overwrite the change on logout, or while another process has it locked.
+pub(crate) fn check_writable(path: &Path, clients: &[RunningClient]) -> AppResult<()> {
+    if !written_by_client(path) {
+        return Ok(());
+    }
+    if let Some(server) = server_of_path(path) {
+        if clients.iter().any(|c| c.server == server) {
+            return Err(client_running_error(server, path));
+        }
+    }
+    check_unlocked(path)
+}
+
+/// `check_writable` for every path, so a multi-file write is refused before it starts.
+pub(crate) fn ensure_writable<P: AsRef<Path>>(paths: impl IntoIterator<Item = P>) -> AppResult<()> {
+    let mut clients = None;
+    for path in paths {
+        let path = path.as_ref();
+        if written_by_client(path) {
+            check_writable(path, clients.get_or_insert_with(running_clients))?;
+        }
+    }
+    Ok(())
+}
+
+/// A write that was refused because a client was running, to be retried once it exits.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum PendingOperation {
+    Copy {
+        source_path: String,
+        target_paths: Vec<String>,
+    },
+    Import {
+        import_path: String,
+        custom_eve_path: Option<String>,
+        overwrite_paths: Vec<String>,
+    },
+    Sections {
+        source_path: String,
+        target_paths: Vec<String>,
+        sections: Vec<String>,
+    },
+    Overview {
+        yaml_path: String,
+        target_paths: Vec<String>,
+    },
+    Shortcuts {
+        import_path: String,
+        target_paths: Vec<String>,
+    },
+    WindowLayout {
+        source_path: String,
+        target_paths: Vec<String>,
+        source_screen: Option<ScreenSpec>,
+        target_screen: ScreenSpec,
+    },
+}
+
+// A queued write to several targets is reported by its first failed target
+fn first_error(results: Vec<TargetResult>) -> AppResult<()> {
+    results
+        .into_iter()
+        .find_map(|r| r.error)
+        .map_or(Ok(()), Err)
+}
+
+impl PendingOperation {
+    fn run(&self, data_dir: &Path) -> AppResult<()> {
+        match self.clone() {
+            PendingOperation::Copy {
+                source_path,
+                target_paths,
+            } => first_error(copy_settings_files(data_dir, source_path, target_paths)?),
+            PendingOperation::Import {
+                import_path,
+                custom_eve_path,
+                overwrite_paths,
+            } => first_error(
+                import_archive(data_dir, import_path, custom_eve_path, overwrite_paths)?.failed,
+            ),
+            PendingOperation::Sections {
+                source_path,
+                target_paths,
+                sections,
+            } => first_error(copy_section_files(
+                data_dir,
+                source_path,
+                target_paths,
+                sections,
+            )?),
+            PendingOperation::Overview {
+                yaml_path,
+                target_paths,
+            } => first_error(import_overview_files(data_dir, yaml_path, target_paths)?),
+            PendingOperation::Shortcuts {
+                import_path,
+                target_paths,
+            } => first_error(import_shortcut_files(data_dir, import_path, target_paths)?),
+            PendingOperation::WindowLayout {
+                source_path,
+                target_paths,
+                source_screen,
+                target_screen,
+            } => first_error(apply_layout_files(
+                data_dir,
+                source_path,
+                target_paths,
+                source_screen,
+                target_screen,
+            )?),
+        }
+    }
+}
+
+#[derive(Serialize, Debug, Clone)]
+pub struct QueuedOperation {
+    pub id: u64,
+    pub operation: PendingOperation,
+    pub queued_at: u64,
+}
+
+/// Result of a queued operation, sent as the `queued-operation-finished` event.
+#[derive(Serialize, Debug, Clone)]
+pub struct QueuedOperationResult {
+    pub id: u64,
+    pub error: Option<AppError>,
+}
+
+#[derive(Default)]
+pub struct OperationQueue {
+    queue: Mutex<Vec<QueuedOperation>>,
+    next_id: Mutex<u64>,
+}
+
+// Runs every queued operation whose servers have no client left. One that is refused
+// again because a client started in between stays queued.
+fn run_queue(app: &tauri::AppHandle, queue: &OperationQueue) {
+    let pending = queue.queue.lock().unwrap().clone();
+    if pending.is_empty() {
+        return;
+    }
+    let data_dir = match app_data_dir(app) {
+        Ok(dir) => dir,
+        Err(_) => return,
+    };
+
+    let mut finished = false;
+    for queued in pending {
+        let error = match queued.operation.run(&data_dir) {
+            Err(e) if e.code == ErrorCode::ClientRunning => continue,
+            result => result.err(),
+        };
+        queue.queue.lock().unwrap().retain(|q| q.id != queued.id);
+        let _ = app.emit(
+            "queued-operation-finished",
+            QueuedOperationResult {
+                id: queued.id,
+                error,
+            },
+        );
+        finished = true;
+    }
+
+    if finished {
+        emit_data_changed(app);
+    }
+}
+
+/// Registers the operation queue and starts the background thread that drains it.
+pub fn init(app: &tauri::AppHandle) {
+    app.manage(OperationQueue::default());
+
+    let app = app.clone();
+    thread::spawn(move || {
+        let queue = app.state::<OperationQueue>();
+        loop {
+            thread::sleep(TICK);
+            run_queue(&app, &queue);
+        }
+    });
+}
+
+#[tauri::command]
+pub fn get_running_clients() -> Vec<RunningClient> {
+    running_clients()
+}
+
+/// Queues a write that was refused with `client_running`. It runs on its own
+/// once the clients it would conflict with have exited.
+#[tauri::command]
+pub fn queue_operation(
+    queue: tauri::State<'_, OperationQueue>,
+    operation: PendingOperation,
+) -> QueuedOperation {
+    let mut next_id = queue.next_id.lock().unwrap();
+    *next_id += 1;
+    let queued = QueuedOperation {
+        id: *next_id,
+        operation,
+        queued_at: SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_secs())
+            .unwrap_or(0),
+    };
+    queue.queue.lock().unwrap().push(queued.clone());
+    queued
+}
+
+#[tauri::command]
+pub fn get_queued_operations(queue: tauri::State<'_, OperationQueue>) -> Vec<QueuedOperation> {
+    queue.queue.lock().unwrap().clone()
+}
+
+#[tauri::command]
+pub fn cancel_queued_operation(queue: tauri::State<'_, OperationQueue>, id: u64) -> bool {
+    let mut queue = queue.queue.lock().unwrap();
+    let before = queue.len();
+    queue.retain(|q| q.id != id);
+    queue.len() != before
+}
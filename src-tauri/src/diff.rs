@@ -0,0 +1,251 @@
+use crate::marshal::Value;
+use crate::sections::{self, SECTIONS};
+use serde::Serialize;
+use std::collections::{HashMap, HashSet};
+
+#[derive(Serialize, Debug, Clone, Copy, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum ChangeKind {
+    Added,
+    Removed,
+    Changed,
+}
+
+#[derive(Serialize, Debug, Clone)]
+pub struct KeyChange {
+    pub group: String,
+    pub key: String,
+    pub kind: ChangeKind,
+    pub old_value: Option<Value>,
+    pub new_value: Option<Value>,
+}
+
+#[derive(Serialize, Debug, Clone)]
+pub struct SectionDiff {
+    /// Catalogue section id, or `None` for groups no section covers.
+    pub section: Option<String>,
+    pub label: String,
+    pub changes: Vec<KeyChange>,
+}
+
+fn key_label(key: &Value) -> String {
+    match key.as_str() {
+        Some(s) => s.to_string(),
+        None => serde_json::to_string(&key.detached()).unwrap_or_default(),
+    }
+}
+
+fn group_entries(group: &Value) -> Entries {
+    match group.as_dict() {
+        Some(entries) => entries
+            .iter()
+            .map(|(k, v)| (key_label(k), v.detached()))
+            .collect(),
+        // A group that is not a dict is compared as a single unnamed entry
+        None => vec![(String::new(), group.detached())],
+    }
+}
+
+type Entries = Vec<(String, Value)>;
+
+fn named_groups(root: &Value) -> Vec<(String, Entries)> {
+    sections::groups(root)
+        .map(|groups| {
+            groups
+                .iter()
+                .map(|(k, v)| (key_label(k), group_entries(v)))
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+fn find_group<'a>(groups: &'a [(String, Entries)], name: &str) -> Option<&'a Entries> {
+    groups
+        .iter()
+        .find(|(n, _)| n == name)
+        .map(|(_, entries)| entries)
+}
+
+/// Compares two decoded settings trees key by key and groups the differences by
+/// catalogue section, in catalogue order, followed by uncovered groups by name.
+pub fn diff_trees(old: &Value, new: &Value) -> Vec<SectionDiff> {
+    let old_groups = named_groups(old);
+    let new_groups = named_groups(new);
+
+    let mut group_names: Vec<&String> = old_groups.iter().map(|(name, _)| name).collect();
+    for (name, _) in &new_groups {
+        if !group_names.contains(&name) {
+            group_names.push(name);
+        }
+    }
+
+    let empty = Vec::new();
+    let mut changes: Vec<KeyChange> = Vec::new();
+
+    for name in group_names {
+        let old_entries = find_group(&old_groups, name).unwrap_or(&empty);
+        let new_entries = find_group(&new_groups, name).unwrap_or(&empty);
+
+        // The first of any duplicate keys wins, on both sides
+        let mut new_by_key: HashMap<&str, &Value> = HashMap::with_capacity(new_entries.len());
+        for (key, value) in new_entries {
+            new_by_key.entry(key).or_insert(value);
+        }
+        let mut old_keys: HashSet<&str> = HashSet::with_capacity(old_entries.len());
+
+        for (key, old_value) in old_entries {
+            if !old_keys.insert(key) {
+                continue;
+            }
+            match new_by_key.get(key.as_str()) {
+                Some(&new_value) if new_value == old_value => {}
+                Some(&new_value) => changes.push(KeyChange {
+                    group: name.clone(),
+                    key: key.clone(),
+                    kind: ChangeKind::Changed,
+                    old_value: Some(old_value.clone()),
+                    new_value: Some(new_value.clone()),
+                }),
+                None => changes.push(KeyChange {
+                    group: name.clone(),
+                    key: key.clone(),
+                    kind: ChangeKind::Removed,
+                    old_value: Some(old_value.clone()),
+                    new_value: None,
+                }),
+            }
+        }
+
+        for (key, new_value) in new_entries {
+            if !old_keys.contains(key.as_str()) && new_by_key.remove(key.as_str()).is_some() {
+                changes.push(KeyChange {
+                    group: name.clone(),
+                    key: key.clone(),
+                    kind: ChangeKind::Added,
+                    old_value: None,
+                    new_value: Some(new_value.clone()),
+                });
+            }
+        }
+    }
+
+    let mut result: Vec<SectionDiff> = Vec::new();
+
+    for section in SECTIONS {
+        let (matched, rest): (Vec<KeyChange>, Vec<KeyChange>) = changes
+            .into_iter()
+            .partition(|c| section.rules.iter().any(|r| r.covers(&c.group, &c.key)));
+        changes = rest;
+
+        if !matched.is_empty() {
+            result.push(SectionDiff {
+                section: Some(section.id.to_string()),
+                label: section.label.to_string(),
+                changes: matched,
+            });
+        }
+    }
+
+    let mut other: Vec<SectionDiff> = Vec::new();
+    for change in changes {
+        match other.iter_mut().find(|d| d.label == change.group) {
+            Some(diff) => diff.changes.push(change),
+            None => other.push(SectionDiff {
+                section: None,
+                label: change.group.clone(),
+                changes: vec![change],
+            }),
+        }
+    }
+    other.sort_by(|a, b| a.label.cmp(&b.label));
+    result.extend(other);
+
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn settings(groups: Vec<(&str, Vec<(&str, Value)>)>) -> Value {
+        Value::Dict(
+            groups
+                .into_iter()
+                .map(|(name, entries)| {
+                    let entries = entries
+                        .into_iter()
+                        .map(|(k, v)| (Value::Str(k.to_string()), v))
+                        .collect();
+                    (Value::Str(name.to_string()), Value::Dict(entries))
+                })
+                .collect(),
+        )
+    }
+
+    fn summary(diffs: &[SectionDiff]) -> Vec<(Option<&str>, &str, ChangeKind)> {
+        diffs
+            .iter()
+            .flat_map(|d| {
+                d.changes
+                    .iter()
+                    .map(|c| (d.section.as_deref(), c.key.as_str(), c.kind))
+            })
+            .collect()
+    }
+
+    #[test]
+    fn added_removed_and_changed_keys_are_reported() {
+        let old = settings(vec![
+            (
+                "audio",
+                vec![
+                    // Stored wider than needed, which is not a change
+                    ("volume", Value::Sized(4, Box::new(Value::Int(5)))),
+                    ("muted", Value::Bool(false)),
+                ],
+            ),
+            (
+                "misc",
+                vec![("gone", Value::Int(1)), ("same", Value::Int(2))],
+            ),
+        ]);
+        let new = settings(vec![
+            (
+                "audio",
+                vec![("muted", Value::Bool(true)), ("volume", Value::Int(5))],
+            ),
+            (
+                "misc",
+                vec![("same", Value::Int(2)), ("fresh", Value::Int(3))],
+            ),
+        ]);
+
+        let diffs = diff_trees(&old, &new);
+        assert_eq!(
+            summary(&diffs),
+            [
+                (Some("audio"), "muted", ChangeKind::Changed),
+                (None, "gone", ChangeKind::Removed),
+                (None, "fresh", ChangeKind::Added),
+            ]
+        );
+        assert_eq!(diffs[0].changes[0].old_value, Some(Value::Bool(false)));
+        assert_eq!(diffs[0].changes[0].new_value, Some(Value::Bool(true)));
+        assert_eq!(diffs[1].label, "misc");
+        assert!(diff_trees(&new, &new).is_empty());
+    }
+
+    #[test]
+    fn whole_groups_appearing_or_disappearing_are_reported_per_key() {
+        let old = settings(vec![("audio", vec![("volume", Value::Int(5))])]);
+        let new = settings(vec![("cmd", vec![("CmdReload", Value::Int(1))])]);
+
+        assert_eq!(
+            summary(&diff_trees(&old, &new)),
+            [
+                (Some("shortcuts"), "CmdReload", ChangeKind::Added),
+                (Some("audio"), "volume", ChangeKind::Removed),
+            ]
+        );
+    }
+}
@@ -0,0 +1,141 @@
+use serde::Serialize;
+use std::fmt;
+use std::io;
+use std::path::Path;
+
+/// What went wrong, as a stable identifier the frontend and scripts can match on.
+/// Variants are never renamed or removed, only added.
+#[derive(Serialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum ErrorCode {
+    NotFound,
+    PermissionDenied,
+    /// Another process, usually a running EVE client, has the file open.
+    FileLocked,
+    AlreadyExists,
+    /// The request itself is wrong: unknown names, mismatched kinds, bad values.
+    InvalidInput,
+    /// A file exists but its contents are corrupt or not in the expected format.
+    InvalidData,
+    /// A valid file this version cannot safely modify.
+    Unsupported,
+    /// Files changed in a way that makes the operation unsafe.
+    Conflict,
+    /// An EVE client for the server is running and would overwrite the change on logout.
+    ClientRunning,
+    Network,
+    Io,
+    Other,
+}
+
+/// Error returned by every command.
+#[derive(Serialize, Debug, Clone)]
+pub struct AppError {
+    pub code: ErrorCode,
+    pub message: String,
+    /// File the error is about, when there is one.
+    pub path: Option<String>,
+    /// Whether the same call may succeed if tried again later.
+    pub retryable: bool,
+}
+
+pub type AppResult<T> = Result<T, AppError>;
+
+impl AppError {
+    pub fn new(code: ErrorCode, message: impl Into<String>) -> Self {
+        Self {
+            code,
+            message: message.into(),
+            path: None,
+            retryable: matches!(
+                code,
+                ErrorCode::FileLocked | ErrorCode::ClientRunning | ErrorCode::Network
+            ),
+        }
+    }
+
+    pub fn not_found(message: impl Into<String>) -> Self {
+        Self::new(ErrorCode::NotFound, message)
+    }
+
+    pub fn invalid_input(message: impl Into<String>) -> Self {
+        Self::new(ErrorCode::InvalidInput, message)
+    }
+
+    pub fn invalid_data(message: impl Into<String>) -> Self {
+        Self::new(ErrorCode::InvalidData, message)
+    }
+
+    pub fn other(message: impl Into<String>) -> Self {
+        Self::new(ErrorCode::Other, message)
+    }
+
+    pub fn with_path(mut self, path: &Path) -> Self {
+        self.path = Some(path.to_string_lossy().into_owned());
+        self
+    }
+
+    /// Error for a failed filesystem call, e.g. `AppError::io("read", path, e)`.
+    pub fn io(action: &str, path: &Path, err: io::Error) -> Self {
+        let code = io_code(&err);
+        let mut error = Self::new(
+            code,
+            format!("Failed to {} {}: {}", action, path.display(), err),
+        )
+        .with_path(path);
+        error.retryable = error.retryable
+            || matches!(
+                err.kind(),
+                io::ErrorKind::Interrupted | io::ErrorKind::TimedOut | io::ErrorKind::WouldBlock
+            );
+        error
+    }
+}
+
+fn io_code(err: &io::Error) -> ErrorCode {
+    // ERROR_SHARING_VIOLATION and ERROR_LOCK_VIOLATION: the client has the file open
+    #[cfg(target_os = "windows")]
+    if matches!(err.raw_os_error(), Some(32) | Some(33)) {
+        return ErrorCode::FileLocked;
+    }
+
+    match err.kind() {
+        io::ErrorKind::NotFound => ErrorCode::NotFound,
+        io::ErrorKind::PermissionDenied => ErrorCode::PermissionDenied,
+        io::ErrorKind::AlreadyExists => ErrorCode::AlreadyExists,
+        io::ErrorKind::WouldBlock => ErrorCode::FileLocked,
+        io::ErrorKind::InvalidData => ErrorCode::InvalidData,
+        _ => ErrorCode::Io,
+    }
+}
+
+/// Attaches the action and path to a failed filesystem call.
+pub trait IoResultExt<T> {
+    fn at(self, action: &str, path: &Path) -> AppResult<T>;
+}
+
+impl<T> IoResultExt<T> for io::Result<T> {
+    fn at(self, action: &str, path: &Path) -> AppResult<T> {
+        self.map_err(|e| AppError::io(action, path, e))
+    }
+}
+
+impl fmt::Display for AppError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.message)
+    }
+}
+
+impl std::error::Error for AppError {}
+
+impl From<AppError> for String {
+    fn from(error: AppError) -> Self {
+        error.message
+    }
+}
+
+impl From<reqwest::Error> for AppError {
+    fn from(err: reqwest::Error) -> Self {
+        Self::new(ErrorCode::Network, format!("Network error: {}", err))
+    }
+}
@@ -1,10 +1,14 @@
+use crate::error::{AppError, AppResult, ErrorCode};
 use reqwest::Client;
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 use std::sync::LazyLock;
+use std::time::{Duration, Instant};
 use tokio::sync::RwLock;
 
 const ESI_BASE: &str = "https://esi.evetech.net/latest";
+/// How long a character ESI does not know is left alone before it is looked up again.
+const UNKNOWN_CHARACTER_RETRY: Duration = Duration::from_secs(15 * 60);
 
 pub static CLIENT: LazyLock<Client> = LazyLock::new(|| {
     Client::builder()
@@ -16,6 +20,10 @@ pub static CLIENT: LazyLock<Client> = LazyLock::new(|| {
 static CHAR_CACHE: LazyLock<RwLock<HashMap<i64, CharacterInfo>>> =
     LazyLock::new(|| RwLock::new(HashMap::new()));
 
+/// Characters ESI answered 404 for, with when that was.
+static UNKNOWN_CHARS: LazyLock<RwLock<HashMap<i64, Instant>>> =
+    LazyLock::new(|| RwLock::new(HashMap::new()));
+
 static CORP_CACHE: LazyLock<RwLock<HashMap<i32, CorporationInfo>>> =
     LazyLock::new(|| RwLock::new(HashMap::new()));
 
@@ -56,23 +64,25 @@ struct EsiCorporationResponse {
     alliance_id: Option<i32>,
 }
 
-async fn fetch_character_from_esi(character_id: i64) -> Result<CharacterInfo, String> {
+async fn fetch_character_from_esi(character_id: i64) -> AppResult<CharacterInfo> {
     let url = format!("{}/characters/{}/", ESI_BASE, character_id);
 
-    let response = CLIENT
-        .get(&url)
-        .send()
-        .await
-        .map_err(|e| format!("Network error: {}", e))?;
+    let response = CLIENT.get(&url).send().await?;
 
+    if response.status() == reqwest::StatusCode::NOT_FOUND {
+        return Err(AppError::not_found(format!("ESI has no {}", url)));
+    }
     if !response.status().is_success() {
-        return Err(format!("ESI returned status {}", response.status()));
+        return Err(AppError::new(
+            ErrorCode::Network,
+            format!("ESI returned status {}", response.status()),
+        ));
     }
 
     let esi_char: EsiCharacterResponse = response
         .json()
         .await
-        .map_err(|e| format!("Failed to parse response: {}", e))?;
+        .map_err(|e| AppError::invalid_data(format!("Failed to parse response: {}", e)))?;
 
     Ok(CharacterInfo {
         character_id,
@@ -85,23 +95,25 @@ async fn fetch_character_from_esi(character_id: i64) -> Result<CharacterInfo, St
     })
 }
 
-async fn fetch_corporation_from_esi(corporation_id: i32) -> Result<CorporationInfo, String> {
+async fn fetch_corporation_from_esi(corporation_id: i32) -> AppResult<CorporationInfo> {
     let url = format!("{}/corporations/{}/", ESI_BASE, corporation_id);
 
-    let response = CLIENT
-        .get(&url)
-        .send()
-        .await
-        .map_err(|e| format!("Network error: {}", e))?;
+    let response = CLIENT.get(&url).send().await?;
 
+    if response.status() == reqwest::StatusCode::NOT_FOUND {
+        return Err(AppError::not_found(format!("ESI has no {}", url)));
+    }
     if !response.status().is_success() {
-        return Err(format!("ESI returned status {}", response.status()));
+        return Err(AppError::new(
+            ErrorCode::Network,
+            format!("ESI returned status {}", response.status()),
+        ));
     }
 
     let esi_corp: EsiCorporationResponse = response
         .json()
         .await
-        .map_err(|e| format!("Failed to parse response: {}", e))?;
+        .map_err(|e| AppError::invalid_data(format!("Failed to parse response: {}", e)))?;
 
     Ok(CorporationInfo {
         corporation_id,
@@ -112,15 +124,37 @@ async fn fetch_corporation_from_esi(corporation_id: i32) -> Result<CorporationIn
     })
 }
 
-pub async fn get_character(character_id: i64) -> Result<CharacterInfo, String> {
+pub async fn get_character(character_id: i64) -> AppResult<CharacterInfo> {
     {
         let cache = CHAR_CACHE.read().await;
         if let Some(info) = cache.get(&character_id) {
             return Ok(info.clone());
         }
     }
+    {
+        let unknown = UNKNOWN_CHARS.read().await;
+        if let Some(checked) = unknown.get(&character_id) {
+            if checked.elapsed() < UNKNOWN_CHARACTER_RETRY {
+                return Err(AppError::not_found(format!(
+                    "ESI has no character {}",
+                    character_id
+                )));
+            }
+        }
+    }
 
-    let mut info = fetch_character_from_esi(character_id).await?;
+    let mut info = match fetch_character_from_esi(character_id).await {
+        Ok(info) => info,
+        Err(e) => {
+            if e.code == ErrorCode::NotFound {
+                UNKNOWN_CHARS
+                    .write()
+                    .await
+                    .insert(character_id, Instant::now());
+            }
+            return Err(e);
+        }
+    };
 
     if let Ok(corp) = get_corporation(info.corporation_id).await {
         info.corporation_name = Some(corp.name);
@@ -134,7 +168,7 @@ pub async fn get_character(character_id: i64) -> Result<CharacterInfo, String> {
     Ok(info)
 }
 
-pub async fn get_corporation(corporation_id: i32) -> Result<CorporationInfo, String> {
+pub async fn get_corporation(corporation_id: i32) -> AppResult<CorporationInfo> {
     {
         let cache = CORP_CACHE.read().await;
         if let Some(info) = cache.get(&corporation_id) {
@@ -1,16 +1,28 @@
+use crate::clients;
+use crate::diff::{self, SectionDiff};
+use crate::error::{AppError, AppResult, ErrorCode, IoResultExt};
 use crate::esi;
+use crate::index;
+use crate::location;
+use crate::marshal;
+use crate::metadata::{self, BackupMetadata};
+use crate::operations::OperationRecorder;
+use crate::prefs;
+use crate::sections::{self, SectionDef};
+use crate::store;
 use serde::{Deserialize, Serialize};
 use sha2::{Digest, Sha256};
-use std::collections::HashMap;
+use std::cmp::Reverse;
+use std::collections::{HashMap, HashSet};
 use std::fs;
 use std::io::{Read, Write};
-use std::path::{Path, PathBuf};
+use std::path::{Component, Path, PathBuf};
 use std::time::{SystemTime, UNIX_EPOCH};
 use tauri::{Emitter, Manager};
 use zip::write::SimpleFileOptions;
 use zip::ZipArchive;
 
-fn emit_data_changed(app: &tauri::AppHandle) {
+pub(crate) fn emit_data_changed(app: &tauri::AppHandle) {
     let _ = app.emit("data-changed", ());
 }
 
@@ -24,7 +36,15 @@ pub enum Server {
 }
 
 impl Server {
-    fn from_folder_name(name: &str) -> Option<Self> {
+    /// Every server, in display order.
+    pub(crate) const ALL: [Server; 4] = [
+        Server::Tranquility,
+        Server::Singularity,
+        Server::Thunderdome,
+        Server::Serenity,
+    ];
+
+    pub(crate) fn from_folder_name(name: &str) -> Option<Self> {
         let lower = name.to_lowercase();
         if lower.contains("tranquility") {
             Some(Server::Tranquility)
@@ -43,7 +63,7 @@ impl Server {
         matches!(self, Server::Tranquility)
     }
 
-    fn display_name(&self) -> &'static str {
+    pub(crate) fn display_name(&self) -> &'static str {
         match self {
             Server::Tranquility => "Tranquility",
             Server::Singularity => "Singularity",
@@ -71,13 +91,22 @@ impl Server {
     }
 }
 
-#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Copy)]
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq, Hash, Copy)]
 #[serde(rename_all = "lowercase")]
 pub enum SettingsKind {
     User,
     Char,
 }
 
+impl SettingsKind {
+    pub(crate) fn file_tag(&self) -> &'static str {
+        match self {
+            SettingsKind::User => "user",
+            SettingsKind::Char => "char",
+        }
+    }
+}
+
 #[derive(Serialize, Debug, Clone)]
 pub struct ServerInfo {
     pub id: Server,
@@ -85,8 +114,13 @@ pub struct ServerInfo {
     pub short_name: String,
     pub color: String,
     pub supports_esi: bool,
+    /// Enabled in every profile of the server.
     pub brackets_always_show: bool,
+    /// Enabled in some profiles and disabled in others.
+    pub brackets_mixed: bool,
     pub server_path: String,
+    /// An EVE client for this server is running, so its settings files are off limits.
+    pub client_running: bool,
 }
 
 #[derive(Serialize, Debug, Clone)]
@@ -114,6 +148,7 @@ pub struct SettingsEntry {
 pub struct ProfileData {
     pub name: String,
     pub path: String,
+    pub brackets_always_show: bool,
     pub accounts: Vec<SettingsEntry>,
     pub characters: Vec<SettingsEntry>,
 }
@@ -135,6 +170,10 @@ pub struct BackupEntry {
     pub original_name: Option<String>,
     pub display_name: String,
     pub relative_time: String,
+    pub notes: Option<String>,
+    pub tags: Vec<String>,
+    pub server: Option<Server>,
+    pub profile: Option<String>,
 }
 
 #[derive(Serialize, Debug, Clone)]
@@ -143,7 +182,7 @@ pub struct AppData {
     pub backups: Vec<BackupEntry>,
 }
 
-fn eve_settings_root(custom_path: Option<&str>) -> Option<PathBuf> {
+pub(crate) fn eve_settings_root(custom_path: Option<&str>) -> Option<PathBuf> {
     if let Some(p) = custom_path {
         let path = PathBuf::from(p);
         if path.is_dir() {
@@ -171,109 +210,39 @@ fn eve_settings_root(custom_path: Option<&str>) -> Option<PathBuf> {
     }
 }
 
-fn backup_directory_for_path(source_path: &PathBuf) -> Result<PathBuf, String> {
-    let profile_dir = source_path
-        .parent() // settings_profile dir (e.g., settings_Default)
-        .ok_or("Could not determine profile directory")?;
-
-    let path = profile_dir.join("backups");
-    fs::create_dir_all(&path).map_err(|e| e.to_string())?;
+fn backup_directory_for_path(source_path: &Path) -> AppResult<PathBuf> {
+    let path = location::backup_dir_for(source_path)?;
+    fs::create_dir_all(&path).at("create", &path)?;
     Ok(path)
 }
 
-fn aliases_file(app: &tauri::AppHandle) -> Result<PathBuf, String> {
-    let mut path = app.path().app_data_dir().map_err(|e| e.to_string())?;
-    fs::create_dir_all(&path).map_err(|e| e.to_string())?;
-    path.push("aliases.json");
+/// Folder for the app's own files: aliases, the operation journal and configuration.
+/// Everything below the command layer takes this path instead of an `AppHandle`, so the
+/// CLI can share it.
+pub(crate) fn app_data_dir(app: &tauri::AppHandle) -> AppResult<PathBuf> {
+    let path = app.path().app_data_dir().map_err(|e| {
+        AppError::not_found(format!("Could not determine the app data directory: {}", e))
+    })?;
+    fs::create_dir_all(&path).at("create", &path)?;
     Ok(path)
 }
 
-fn load_aliases(app: &tauri::AppHandle) -> HashMap<String, String> {
-    let path = match aliases_file(app) {
-        Ok(p) => p,
-        Err(_) => return HashMap::new(),
-    };
+fn aliases_file(data_dir: &Path) -> PathBuf {
+    data_dir.join("aliases.json")
+}
 
-    match fs::read_to_string(&path) {
+pub(crate) fn load_aliases(data_dir: &Path) -> HashMap<String, String> {
+    match fs::read_to_string(aliases_file(data_dir)) {
         Ok(content) => serde_json::from_str(&content).unwrap_or_default(),
         Err(_) => HashMap::new(),
     }
 }
 
-fn save_aliases(app: &tauri::AppHandle, aliases: &HashMap<String, String>) -> Result<(), String> {
-    let path = aliases_file(app)?;
-    let content = serde_json::to_string_pretty(aliases).map_err(|e| e.to_string())?;
-    fs::write(&path, content).map_err(|e| e.to_string())?;
-    Ok(())
-}
-
-fn read_brackets_setting(server_path: &PathBuf) -> bool {
-    // Check all profile folders (settings_*) for the setting
-    if let Ok(entries) = fs::read_dir(server_path) {
-        for entry in entries.flatten() {
-            let path = entry.path();
-            let name = path.file_name().and_then(|n| n.to_str()).unwrap_or("");
-            if path.is_dir() && name.starts_with("settings_") {
-                let prefs_path = path.join("prefs.ini");
-                if let Ok(content) = fs::read_to_string(&prefs_path) {
-                    for line in content.lines() {
-                        let trimmed = line.trim();
-                        if trimmed.starts_with("bracketsAlwaysShowShipText=") {
-                            if let Some(value) = trimmed.strip_prefix("bracketsAlwaysShowShipText=")
-                            {
-                                if value.trim() == "1" {
-                                    return true;
-                                }
-                            }
-                        }
-                    }
-                }
-            }
-        }
-    }
-    false
-}
-
-fn write_brackets_setting(server_path: &PathBuf, enabled: bool) -> Result<(), String> {
-    let setting_line = format!(
-        "bracketsAlwaysShowShipText={}",
-        if enabled { "1" } else { "0" }
-    );
-
-    // Apply to all profile folders (settings_*)
-    let entries = fs::read_dir(server_path).map_err(|e| e.to_string())?;
-    for entry in entries.flatten() {
-        let path = entry.path();
-        let name = path.file_name().and_then(|n| n.to_str()).unwrap_or("");
-        if path.is_dir() && name.starts_with("settings_") {
-            let prefs_path = path.join("prefs.ini");
-
-            let content = if prefs_path.exists() {
-                let existing = fs::read_to_string(&prefs_path).map_err(|e| e.to_string())?;
-                let mut found = false;
-                let mut lines: Vec<String> = existing
-                    .lines()
-                    .map(|line| {
-                        if line.trim().starts_with("bracketsAlwaysShowShipText=") {
-                            found = true;
-                            setting_line.clone()
-                        } else {
-                            line.to_string()
-                        }
-                    })
-                    .collect();
-
-                if !found {
-                    lines.push(setting_line.clone());
-                }
-                lines.join("\n")
-            } else {
-                setting_line.clone()
-            };
-
-            fs::write(&prefs_path, content).map_err(|e| e.to_string())?;
-        }
-    }
+fn save_aliases(data_dir: &Path, aliases: &HashMap<String, String>) -> AppResult<()> {
+    let path = aliases_file(data_dir);
+    let content =
+        serde_json::to_string_pretty(aliases).map_err(|e| AppError::other(e.to_string()))?;
+    fs::write(&path, content).at("write", &path)?;
     Ok(())
 }
 
@@ -305,13 +274,7 @@ struct RawSettingsFile {
     modified_time: u64,
 }
 
-fn parse_settings_file(
-    path: &PathBuf,
-    server: Server,
-    profile_name: &str,
-) -> Option<RawSettingsFile> {
-    let filename = path.file_name()?.to_str()?;
-
+pub(crate) fn parse_settings_filename(filename: &str) -> Option<(SettingsKind, &str)> {
     if !filename.starts_with("core_") || !filename.ends_with(".dat") {
         return None;
     }
@@ -331,6 +294,13 @@ fn parse_settings_file(
         _ => return None,
     };
 
+    Some((kind, id))
+}
+
+fn parse_settings_file(path: &Path, server: Server, profile_name: &str) -> Option<RawSettingsFile> {
+    let filename = path.file_name()?.to_str()?;
+    let (kind, id) = parse_settings_filename(filename)?;
+
     let modified_time = fs::metadata(path)
         .and_then(|m| m.modified())
         .ok()
@@ -348,10 +318,45 @@ fn parse_settings_file(
     })
 }
 
-fn scan_installations(
-    custom_eve_path: Option<&str>,
-) -> Result<(HashMap<Server, Vec<ProfileData>>, HashMap<Server, PathBuf>), String> {
-    let root = eve_settings_root(custom_eve_path).ok_or("EVE settings directory not found")?;
+fn settings_entry(f: RawSettingsFile) -> SettingsEntry {
+    SettingsEntry {
+        display_name: f.id.clone(),
+        relative_time: format_relative_time(f.modified_time),
+        modified_time: f.modified_time,
+        path: f.path,
+        id: f.id,
+        kind: f.kind,
+        server: f.server,
+        profile: f.profile,
+        character: None,
+        alias: None,
+    }
+}
+
+/// Entry for a single settings file inside a server's profile folder, with its alias
+/// but without ESI details.
+pub(crate) fn settings_entry_at(
+    path: &Path,
+    aliases: &HashMap<String, String>,
+) -> Option<SettingsEntry> {
+    let profile_dir = path.parent()?;
+    let profile = profile_dir
+        .file_name()?
+        .to_str()?
+        .strip_prefix("settings_")?;
+    let server = Server::from_folder_name(profile_dir.parent()?.file_name()?.to_str()?)?;
+
+    let mut entry = settings_entry(parse_settings_file(path, server, profile)?);
+    apply_alias(&mut entry, aliases);
+    Some(entry)
+}
+
+/// Profiles found for each server, and each server's settings folder.
+pub(crate) type Installations = (HashMap<Server, Vec<ProfileData>>, HashMap<Server, PathBuf>);
+
+pub(crate) fn scan_installations(custom_eve_path: Option<&str>) -> AppResult<Installations> {
+    let root = eve_settings_root(custom_eve_path)
+        .ok_or_else(|| AppError::not_found("EVE settings directory not found"))?;
 
     if !root.exists() {
         return Ok((HashMap::new(), HashMap::new()));
@@ -359,7 +364,8 @@ fn scan_installations(
 
     let mut server_profiles: HashMap<Server, Vec<ProfileData>> = HashMap::new();
     let mut server_paths: HashMap<Server, PathBuf> = HashMap::new();
-    let entries = fs::read_dir(&root).map_err(|e| e.to_string())?;
+    let mut found_profiles = HashSet::new();
+    let entries = fs::read_dir(&root).at("read", &root)?;
 
     for entry in entries.flatten() {
         let path = entry.path();
@@ -417,41 +423,16 @@ fn scan_installations(
             let profile = ProfileData {
                 name: profile_name.to_string(),
                 path: sub_path.to_string_lossy().into_owned(),
-                accounts: accounts
-                    .into_iter()
-                    .map(|f| SettingsEntry {
-                        display_name: f.id.clone(),
-                        relative_time: format_relative_time(f.modified_time),
-                        modified_time: f.modified_time,
-                        path: f.path,
-                        id: f.id,
-                        kind: f.kind,
-                        server: f.server,
-                        profile: f.profile,
-                        character: None,
-                        alias: None,
-                    })
-                    .collect(),
-                characters: characters
-                    .into_iter()
-                    .map(|f| SettingsEntry {
-                        display_name: f.id.clone(),
-                        relative_time: format_relative_time(f.modified_time),
-                        modified_time: f.modified_time,
-                        path: f.path,
-                        id: f.id,
-                        kind: f.kind,
-                        server: f.server,
-                        profile: f.profile,
-                        character: None,
-                        alias: None,
-                    })
-                    .collect(),
+                brackets_always_show: index::profile_flag(&sub_path, prefs::BRACKETS_ALWAYS_SHOW),
+                accounts: accounts.into_iter().map(settings_entry).collect(),
+                characters: characters.into_iter().map(settings_entry).collect(),
             };
 
             server_profiles.entry(server).or_default().push(profile);
+            found_profiles.insert(sub_path);
         }
     }
+    index::retain_profiles(&found_profiles);
 
     for profiles in server_profiles.values_mut() {
         profiles.sort_by(|a, b| a.name.cmp(&b.name));
@@ -460,13 +441,96 @@ fn scan_installations(
     Ok((server_profiles, server_paths))
 }
 
-fn scan_backups(custom_eve_path: Option<&str>) -> Result<Vec<BackupEntry>, String> {
+// Backups are named {name}_{kind}_{id}_{timestamp}.bak
+pub(crate) fn parse_backup_filename(filename: &str) -> Option<(String, SettingsKind, String, u64)> {
+    let stem = filename.strip_suffix(".bak")?;
+    let parts: Vec<&str> = stem.rsplitn(4, '_').collect();
+
+    if parts.len() < 4 {
+        return None;
+    }
+
+    let timestamp = parts[0].parse::<u64>().unwrap_or(0);
+    let original_id = parts[1].to_string();
+    let kind = match parts[2] {
+        "user" => SettingsKind::User,
+        "char" => SettingsKind::Char,
+        _ => return None,
+    };
+    let name = parts[3].to_string();
+
+    Some((name, kind, original_id, timestamp))
+}
+
+/// Server and profile a backup belongs to, from its <server>/settings_<profile>/backups
+/// location.
+fn backup_origin(backup: &Path) -> (Option<Server>, Option<String>) {
+    let profile_dir = backup.parent().and_then(|p| p.parent());
+    let profile = profile_dir
+        .and_then(|p| p.file_name())
+        .and_then(|n| n.to_str())
+        .and_then(|n| n.strip_prefix("settings_"))
+        .map(str::to_string);
+    let server = profile_dir
+        .and_then(|p| p.parent())
+        .and_then(|p| p.file_name())
+        .and_then(|n| n.to_str())
+        .and_then(Server::from_folder_name);
+    (server, profile)
+}
+
+/// Metadata from the backup's sidecar, or reconstructed from its filename for backups
+/// made before sidecars existed.
+pub(crate) fn backup_metadata(path: &Path) -> Option<BackupMetadata> {
+    if let Some(metadata) = metadata::read_metadata(path) {
+        return Some(metadata);
+    }
+
+    let filename = path.file_name()?.to_str()?;
+    let (name, kind, original_id, timestamp) = parse_backup_filename(filename)?;
+    let (server, profile) = backup_origin(path);
+
+    Some(BackupMetadata {
+        name,
+        kind,
+        original_id,
+        timestamp,
+        notes: None,
+        tags: Vec::new(),
+        source_path: None,
+        server,
+        profile,
+        character_name: None,
+        sha256: None,
+        app_version: None,
+    })
+}
+
+pub(crate) fn backup_entry(path: &Path, metadata: BackupMetadata) -> BackupEntry {
+    BackupEntry {
+        id: format!("{}_{}", metadata.name, metadata.timestamp),
+        display_name: metadata.name.clone(),
+        path: path.to_string_lossy().into_owned(),
+        timestamp: metadata.timestamp,
+        kind: metadata.kind,
+        original_id: metadata.original_id,
+        original_name: metadata.character_name,
+        relative_time: format_relative_time(metadata.timestamp),
+        notes: metadata.notes,
+        tags: metadata.tags,
+        server: metadata.server,
+        profile: metadata.profile,
+        name: metadata.name,
+    }
+}
+
+pub(crate) fn scan_backups(custom_eve_path: Option<&str>) -> AppResult<Vec<BackupEntry>> {
     let eve_root = match eve_settings_root(custom_eve_path) {
         Some(r) => r,
         None => return Ok(Vec::new()),
     };
 
-    let mut backups = Vec::new();
+    let mut backup_dirs = location::external_backup_dirs();
 
     let server_dirs = match fs::read_dir(&eve_root) {
         Ok(e) => e,
@@ -499,78 +563,74 @@ fn scan_backups(custom_eve_path: Option<&str>) -> Result<Vec<BackupEntry>, Strin
             }
 
             let backup_dir = profile_path.join("backups");
-            let entries = match fs::read_dir(&backup_dir) {
-                Ok(e) => e,
-                Err(_) => continue,
-            };
+            if backup_dir.is_dir() {
+                backup_dirs.push(backup_dir);
+            }
+        }
+    }
 
-            for entry in entries.flatten() {
-                let path = entry.path();
-                let filename = match path.file_name().and_then(|n| n.to_str()) {
-                    Some(f) if f.ends_with(".bak") => f,
-                    _ => continue,
-                };
+    // The backup root may itself be inside the EVE folder
+    backup_dirs.sort();
+    backup_dirs.dedup();
 
-                let stem = filename.trim_end_matches(".bak");
-                let parts: Vec<&str> = stem.rsplitn(4, '_').collect();
+    let mut backups = Vec::new();
+    let mut found = HashSet::new();
+    for backup_dir in backup_dirs {
+        let entries = match fs::read_dir(&backup_dir) {
+            Ok(e) => e,
+            Err(_) => continue,
+        };
 
-                if parts.len() < 4 {
-                    continue;
-                }
+        for entry in entries.flatten() {
+            let path = entry.path();
+            if path.extension().and_then(|e| e.to_str()) != Some("bak") {
+                continue;
+            }
 
-                let timestamp = parts[0].parse::<u64>().unwrap_or(0);
-                let original_id = parts[1].to_string();
-                let kind = match parts[2] {
-                    "user" => SettingsKind::User,
-                    "char" => SettingsKind::Char,
-                    _ => continue,
-                };
-                let name = parts[3].to_string();
-
-                backups.push(BackupEntry {
-                    id: format!("{}_{}", name, timestamp),
-                    display_name: name.clone(),
-                    name,
-                    path: path.to_string_lossy().into_owned(),
-                    timestamp,
-                    kind,
-                    original_id,
-                    original_name: None,
-                    relative_time: format_relative_time(timestamp),
-                });
+            if let Some(backup) = index::backup(&path) {
+                backups.push(backup);
             }
+            found.insert(path);
         }
     }
+    index::retain_backups(&found);
 
-    backups.sort_by(|a, b| b.timestamp.cmp(&a.timestamp));
+    backups.sort_by_key(|b| Reverse(b.timestamp));
     Ok(backups)
 }
 
+pub(crate) fn apply_aliases(
+    server_profiles: &mut HashMap<Server, Vec<ProfileData>>,
+    aliases: &HashMap<String, String>,
+) {
+    for profiles in server_profiles.values_mut() {
+        for profile in profiles.iter_mut() {
+            for entry in profile
+                .accounts
+                .iter_mut()
+                .chain(profile.characters.iter_mut())
+            {
+                apply_alias(entry, aliases);
+            }
+        }
+    }
+}
+
+fn apply_alias(entry: &mut SettingsEntry, aliases: &HashMap<String, String>) {
+    if let Some(alias) = aliases.get(&entry.id) {
+        entry.alias = Some(alias.clone());
+        entry.display_name = alias.clone();
+    }
+}
+
 #[tauri::command]
 pub async fn get_app_data(
     app: tauri::AppHandle,
     custom_eve_path: Option<String>,
-) -> Result<AppData, String> {
+) -> AppResult<AppData> {
     let (mut server_profiles, server_paths) = scan_installations(custom_eve_path.as_deref())?;
     let mut backups = scan_backups(custom_eve_path.as_deref())?;
-    let aliases = load_aliases(&app);
-
-    for profiles in server_profiles.values_mut() {
-        for profile in profiles.iter_mut() {
-            for account in profile.accounts.iter_mut() {
-                if let Some(alias) = aliases.get(&account.id) {
-                    account.alias = Some(alias.clone());
-                    account.display_name = alias.clone();
-                }
-            }
-            for character in profile.characters.iter_mut() {
-                if let Some(alias) = aliases.get(&character.id) {
-                    character.alias = Some(alias.clone());
-                    character.display_name = alias.clone();
-                }
-            }
-        }
-    }
+    apply_aliases(&mut server_profiles, &load_aliases(&app_data_dir(&app)?));
 
     for server in [Server::Tranquility, Server::Singularity] {
         if let Some(profiles) = server_profiles.get_mut(&server) {
@@ -596,19 +656,13 @@ pub async fn get_app_data(
         }
     }
 
+    let clients = clients::running_clients();
     let mut servers: Vec<ServerData> = Vec::new();
-    let all_servers = [
-        Server::Tranquility,
-        Server::Singularity,
-        Server::Thunderdome,
-        Server::Serenity,
-    ];
-
-    for server in all_servers {
+    for server in Server::ALL {
         if let Some(profiles) = server_profiles.remove(&server) {
             if !profiles.is_empty() {
                 let server_path = server_paths.get(&server).cloned().unwrap_or_default();
-                let brackets_always_show = read_brackets_setting(&server_path);
+                let enabled = profiles.iter().filter(|p| p.brackets_always_show).count();
                 servers.push(ServerData {
                     info: ServerInfo {
                         id: server,
@@ -616,8 +670,10 @@ pub async fn get_app_data(
                         short_name: server.short_name().to_string(),
                         color: server.color().to_string(),
                         supports_esi: server.supports_esi(),
-                        brackets_always_show,
+                        brackets_always_show: enabled == profiles.len(),
+                        brackets_mixed: enabled > 0 && enabled < profiles.len(),
                         server_path: server_path.to_string_lossy().into_owned(),
+                        client_running: clients.iter().any(|c| c.server == server),
                     },
                     profiles,
                 });
@@ -625,27 +681,39 @@ pub async fn get_app_data(
         }
     }
 
-    // Enrich backups with entity names from settings entries
+    fill_original_names(
+        &mut backups,
+        servers.iter().flat_map(|server| server.profiles.iter()),
+    );
+
+    Ok(AppData { servers, backups })
+}
+
+/// Enriches backups with entity names from settings entries. Live names win; the name
+/// recorded in the sidecar covers entities that are gone.
+pub(crate) fn fill_original_names<'a>(
+    backups: &mut [BackupEntry],
+    profiles: impl IntoIterator<Item = &'a ProfileData>,
+) {
+    let mut live_names: HashMap<(SettingsKind, &str), &str> = HashMap::new();
+    for profile in profiles {
+        // Without an alias or ESI name the display name is just the id
+        for entry in profile
+            .accounts
+            .iter()
+            .chain(profile.characters.iter())
+            .filter(|entry| entry.display_name != entry.id)
+        {
+            live_names
+                .entry((entry.kind, entry.id.as_str()))
+                .or_insert(entry.display_name.as_str());
+        }
+    }
     for backup in backups.iter_mut() {
-        for server in &servers {
-            for profile in &server.profiles {
-                let entries = if backup.kind == SettingsKind::User {
-                    &profile.accounts
-                } else {
-                    &profile.characters
-                };
-                if let Some(entry) = entries.iter().find(|e| e.id == backup.original_id) {
-                    backup.original_name = Some(entry.display_name.clone());
-                    break;
-                }
-            }
-            if backup.original_name.is_some() {
-                break;
-            }
+        if let Some(name) = live_names.get(&(backup.kind, backup.original_id.as_str())) {
+            backup.original_name = Some(name.to_string());
         }
     }
-
-    Ok(AppData { servers, backups })
 }
 
 #[tauri::command]
@@ -653,70 +721,97 @@ pub fn create_backup(
     app: tauri::AppHandle,
     source_path: String,
     backup_name: String,
-) -> Result<BackupEntry, String> {
-    let source = PathBuf::from(&source_path);
+    character_name: Option<String>,
+) -> AppResult<BackupEntry> {
+    let entry = backup_settings_file(
+        Path::new(&source_path),
+        &backup_name,
+        character_name.as_deref(),
+    )?;
+    emit_data_changed(&app);
+    Ok(entry)
+}
 
+pub(crate) fn backup_settings_file(
+    source: &Path,
+    backup_name: &str,
+    character_name: Option<&str>,
+) -> AppResult<BackupEntry> {
     if !source.exists() {
-        return Err("Source file does not exist".into());
-    }
-
-    let filename = source
-        .file_name()
-        .and_then(|n| n.to_str())
-        .ok_or("Invalid filename")?;
-
-    if !filename.starts_with("core_") || !filename.ends_with(".dat") {
-        return Err("Invalid settings file".into());
+        return Err(AppError::not_found("Source file does not exist").with_path(source));
     }
 
-    let stem = filename
-        .trim_start_matches("core_")
-        .trim_end_matches(".dat");
-    let (kind_str, id) = stem.split_once('_').ok_or("Invalid settings file format")?;
-
-    let kind = match kind_str {
-        "user" => SettingsKind::User,
-        "char" => SettingsKind::Char,
-        _ => return Err("Unknown settings type".into()),
-    };
+    let filename = source.file_name().and_then(|n| n.to_str()).unwrap_or("");
+    let (kind, id) = parse_settings_filename(filename)
+        .ok_or_else(|| AppError::invalid_input("Invalid settings file").with_path(source))?;
+    let kind_str = kind.file_tag();
 
     let timestamp = SystemTime::now()
         .duration_since(UNIX_EPOCH)
-        .map_err(|e| e.to_string())?
+        .map_err(|e| AppError::other(e.to_string()))?
         .as_secs();
 
-    let backup_filename = format!("{}_{}_{}_{}.bak", backup_name, kind_str, id, timestamp);
+    let backup_filename = format!(
+        "{}_{}_{}_{}.bak",
+        metadata::filename_safe(backup_name),
+        kind_str,
+        id,
+        timestamp
+    );
 
-    let backup_dir = backup_directory_for_path(&source)?;
+    let backup_dir = backup_directory_for_path(source)?;
     let dest = backup_dir.join(&backup_filename);
 
-    fs::copy(&source, &dest).map_err(|e| e.to_string())?;
+    let data = fs::read(source).at("read", source)?;
+    store::store_backup(&dest, &data)?;
 
-    let entry = BackupEntry {
-        id: format!("{}_{}", backup_name, timestamp),
-        display_name: backup_name.clone(),
-        name: backup_name,
-        path: dest.to_string_lossy().into_owned(),
-        timestamp,
+    let (server, profile) = backup_origin(&dest);
+    let metadata = BackupMetadata {
+        name: backup_name.to_string(),
         kind,
         original_id: id.to_string(),
-        original_name: None,
-        relative_time: format_relative_time(timestamp),
+        timestamp,
+        notes: None,
+        tags: Vec::new(),
+        source_path: Some(source.to_string_lossy().into_owned()),
+        server,
+        profile,
+        character_name: character_name.map(str::to_string),
+        sha256: Some(sha256_of_bytes(&data)),
+        app_version: Some(env!("CARGO_PKG_VERSION").to_string()),
     };
+    metadata::write_metadata(&dest, &metadata)?;
 
-    emit_data_changed(&app);
-    Ok(entry)
+    Ok(backup_entry(&dest, metadata))
 }
 
 #[tauri::command]
-pub fn delete_backup(app: tauri::AppHandle, backup_path: String) -> Result<(), String> {
+pub fn delete_backup(app: tauri::AppHandle, backup_path: String) -> AppResult<()> {
     let path = PathBuf::from(&backup_path);
 
     if !path.exists() {
-        return Err("Backup file not found".into());
+        return Err(AppError::not_found("Backup file not found").with_path(&path));
+    }
+
+    let sidecar = metadata::sidecar_path(&path);
+    let data_dir = app_data_dir(&app)?;
+    let mut recorder = OperationRecorder::begin(
+        &data_dir,
+        "delete_backup",
+        serde_json::json!({ "backup_path": backup_path }),
+    )?;
+    recorder.snapshot(&path)?;
+    if sidecar.exists() {
+        recorder.snapshot(&sidecar)?;
+        fs::remove_file(&sidecar).at("delete", &sidecar)?;
     }
+    fs::remove_file(&path).at("delete", &path)?;
+    drop(recorder);
 
-    fs::remove_file(path).map_err(|e| e.to_string())?;
+    // The backup is gone either way; a blob left behind is collected next time
+    for dir in store::store_dirs(&[backup_path.as_str()]) {
+        let _ = store::collect_garbage(&dir, &data_dir);
+    }
     emit_data_changed(&app);
     Ok(())
 }
@@ -726,17 +821,36 @@ pub fn copy_settings(
     app: tauri::AppHandle,
     source_path: String,
     target_paths: Vec<String>,
-) -> Result<u32, String> {
+) -> AppResult<Vec<TargetResult>> {
+    let results = copy_settings_files(&app_data_dir(&app)?, source_path, target_paths)?;
+    if results.iter().any(|r| r.error.is_none()) {
+        emit_data_changed(&app);
+    }
+    Ok(results)
+}
+
+pub(crate) fn copy_settings_files(
+    data_dir: &Path,
+    source_path: String,
+    target_paths: Vec<String>,
+) -> AppResult<Vec<TargetResult>> {
     use filetime::FileTime;
 
     let src = PathBuf::from(&source_path);
 
     if !src.exists() {
-        return Err("Source file not found".into());
+        return Err(AppError::not_found("Source file not found").with_path(&src));
     }
-
-    let mut success_count = 0u32;
+    clients::ensure_writable(&target_paths)?;
+
+    let mut recorder = OperationRecorder::begin(
+        data_dir,
+        "copy_settings",
+        serde_json::json!({ "source_path": source_path, "target_paths": target_paths }),
+    )?;
+    let content = store::content_path(&src)?;
     let now = FileTime::now();
+    let mut results = Vec::with_capacity(target_paths.len());
 
     for target_path in target_paths {
         let dest = PathBuf::from(&target_path);
@@ -745,16 +859,19 @@ pub fn copy_settings(
             continue;
         }
 
-        if fs::copy(&src, &dest).is_ok() {
-            let _ = filetime::set_file_mtime(&dest, now);
-            success_count += 1;
-        }
+        let copied = recorder
+            .snapshot(&dest)
+            .and_then(|()| fs::copy(&content, &dest).at("write", &dest))
+            .and_then(|_| {
+                filetime::set_file_mtime(&dest, now).at("set modification time of", &dest)
+            });
+        results.push(TargetResult {
+            path: target_path,
+            error: copied.err(),
+        });
     }
 
-    if success_count > 0 {
-        emit_data_changed(&app);
-    }
-    Ok(success_count)
+    Ok(results)
 }
 
 #[tauri::command]
@@ -762,8 +879,14 @@ pub fn set_alias(
     app: tauri::AppHandle,
     account_id: String,
     alias: Option<String>,
-) -> Result<(), String> {
-    let mut aliases = load_aliases(&app);
+) -> AppResult<()> {
+    let data_dir = app_data_dir(&app)?;
+    let mut recorder = OperationRecorder::begin(
+        &data_dir,
+        "set_alias",
+        serde_json::json!({ "account_id": account_id, "alias": alias }),
+    )?;
+    let mut aliases = load_aliases(&data_dir);
 
     match alias {
         Some(a) if !a.trim().is_empty() => {
@@ -774,23 +897,341 @@ pub fn set_alias(
         }
     }
 
-    save_aliases(&app, &aliases)?;
+    recorder.snapshot(&aliases_file(&data_dir))?;
+    save_aliases(&data_dir, &aliases)?;
     emit_data_changed(&app);
     Ok(())
 }
 
+// ── Settings tree ────────────────────────────────────────────────────────
+
+#[derive(Serialize, Debug, Clone)]
+pub struct SettingsTree {
+    pub path: String,
+    pub size: u64,
+    pub root: marshal::Value,
+}
+
+pub(crate) fn read_settings_document(path: &Path) -> AppResult<marshal::Document> {
+    let data = store::read_contents(path)?;
+    decode_settings(path, &data)
+}
+
+/// Reads a settings file that sections are copied out of.
+pub(crate) fn read_source_document(path: &Path) -> AppResult<marshal::Document> {
+    let doc = read_settings_document(path)?;
+    if sections::groups(&doc.root).is_none() {
+        return Err(AppError::invalid_data("Settings have no group table").with_path(path));
+    }
+    Ok(doc)
+}
+
+pub(crate) fn decode_settings(path: &Path, data: &[u8]) -> AppResult<marshal::Document> {
+    marshal::decode(data).map_err(|e| {
+        AppError::invalid_data(format!("Failed to decode {}: {}", path.display(), e))
+            .with_path(path)
+    })
+}
+
+fn encode_settings(path: &Path, doc: &marshal::Document) -> AppResult<Vec<u8>> {
+    marshal::encode(doc).map_err(|e| {
+        AppError::invalid_data(format!("Failed to encode {}: {}", path.display(), e))
+            .with_path(path)
+    })
+}
+
+/// Decodes a settings file that is about to be modified. Refuses files the encoder
+/// cannot reproduce byte-for-byte, so a write never silently changes anything
+/// beyond the edited values.
+pub(crate) fn read_editable_settings(path: &Path) -> AppResult<marshal::Document> {
+    let data = fs::read(path).at("read", path)?;
+    let doc = decode_settings(path, &data)?;
+
+    if encode_settings(path, &doc)? != data {
+        return Err(AppError::new(
+            ErrorCode::Unsupported,
+            format!(
+                "{} uses an encoding EVE Wrench cannot reproduce exactly; refusing to modify it",
+                path.display()
+            ),
+        )
+        .with_path(path));
+    }
+    Ok(doc)
+}
+
+pub(crate) fn write_settings_document(path: &Path, doc: &marshal::Document) -> AppResult<()> {
+    let data = encode_settings(path, doc)?;
+
+    // Make sure what we are about to write reads back as the tree we meant to write
+    let written = decode_settings(path, &data)?;
+    if written.root.detached() != doc.root.detached() {
+        return Err(
+            AppError::invalid_data("Encoded settings do not match the edited tree").with_path(path),
+        );
+    }
+
+    let tmp = path.with_extension("dat.tmp");
+    fs::write(&tmp, &data).at("write", &tmp)?;
+    fs::rename(&tmp, path).map_err(|e| {
+        let _ = fs::remove_file(&tmp);
+        AppError::io("replace", path, e)
+    })
+}
+
 #[tauri::command]
-pub fn set_brackets_always_show(
+pub fn read_settings_tree(path: String) -> AppResult<SettingsTree> {
+    let file = PathBuf::from(&path);
+    // Widths are an encoding detail; `write_settings_tree` puts them back
+    let root = read_settings_document(&file)?.root.without_widths();
+    let size = store::content_size(&file);
+
+    Ok(SettingsTree { path, size, root })
+}
+
+#[tauri::command]
+pub fn write_settings_tree(
     app: tauri::AppHandle,
-    server_path: String,
-    enabled: bool,
-) -> Result<(), String> {
-    let path = PathBuf::from(&server_path);
-    write_brackets_setting(&path, enabled)?;
+    path: String,
+    root: marshal::Value,
+) -> AppResult<()> {
+    let file = PathBuf::from(&path);
+    let mut doc = read_editable_settings(&file)?;
+    let mut root = root;
+    root.restore_widths(&doc.root);
+    doc.root = root;
+
+    let mut recorder = OperationRecorder::begin(
+        &app_data_dir(&app)?,
+        "write_settings_tree",
+        serde_json::json!({ "path": path }),
+    )?;
+    recorder.snapshot(&file)?;
+    write_settings_document(&file, &doc)?;
     emit_data_changed(&app);
     Ok(())
 }
 
+#[tauri::command]
+pub fn get_settings_sections() -> &'static [SectionDef] {
+    sections::SECTIONS
+}
+
+fn merge_sections_into(
+    source: &marshal::Value,
+    dest: &Path,
+    selected: &[&SectionDef],
+    recorder: &mut OperationRecorder,
+) -> AppResult<()> {
+    let mut doc = read_editable_settings(dest)?;
+
+    let mut changed = 0;
+    for section in selected {
+        changed += sections::copy_section(source, &mut doc.root, section)
+            .map_err(|e| e.with_path(dest))?;
+    }
+
+    if changed > 0 {
+        recorder.snapshot(dest)?;
+        write_settings_document(dest, &doc)?;
+    }
+    Ok(())
+}
+
+#[tauri::command]
+pub fn copy_settings_sections(
+    app: tauri::AppHandle,
+    source_path: String,
+    target_paths: Vec<String>,
+    sections: Vec<String>,
+) -> AppResult<Vec<TargetResult>> {
+    let results = copy_section_files(&app_data_dir(&app)?, source_path, target_paths, sections)?;
+    if results.iter().any(|r| r.error.is_none()) {
+        emit_data_changed(&app);
+    }
+    Ok(results)
+}
+
+pub(crate) fn copy_section_files(
+    data_dir: &Path,
+    source_path: String,
+    target_paths: Vec<String>,
+    sections: Vec<String>,
+) -> AppResult<Vec<TargetResult>> {
+    let src = PathBuf::from(&source_path);
+
+    if !src.exists() {
+        return Err(AppError::not_found("Source file not found").with_path(&src));
+    }
+
+    let selected = sections
+        .iter()
+        .map(|id| {
+            sections::find_section(id)
+                .ok_or_else(|| AppError::invalid_input(format!("Unknown settings section: {}", id)))
+        })
+        .collect::<AppResult<Vec<_>>>()?;
+    clients::ensure_writable(&target_paths)?;
+
+    let source = read_source_document(&src)?;
+    let mut recorder = OperationRecorder::begin(
+        data_dir,
+        "copy_settings_sections",
+        serde_json::json!({
+            "source_path": source_path,
+            "target_paths": target_paths,
+            "sections": sections,
+        }),
+    )?;
+    let mut results = Vec::with_capacity(target_paths.len());
+
+    for target_path in target_paths {
+        let dest = PathBuf::from(&target_path);
+
+        if src == dest {
+            continue;
+        }
+
+        let error = merge_sections_into(&source.root, &dest, &selected, &mut recorder).err();
+        results.push(TargetResult {
+            path: target_path,
+            error,
+        });
+    }
+
+    Ok(results)
+}
+
+#[derive(Serialize, Debug, Clone)]
+pub struct SettingsDiff {
+    pub left_path: String,
+    pub right_path: String,
+    pub change_count: usize,
+    pub sections: Vec<SectionDiff>,
+}
+
+#[tauri::command]
+pub fn diff_settings(left_path: String, right_path: String) -> AppResult<SettingsDiff> {
+    let left = read_settings_document(Path::new(&left_path))?;
+    let right = read_settings_document(Path::new(&right_path))?;
+
+    let sections = diff::diff_trees(&left.root, &right.root);
+    let change_count = sections.iter().map(|s| s.changes.len()).sum();
+
+    Ok(SettingsDiff {
+        left_path,
+        right_path,
+        change_count,
+        sections,
+    })
+}
+
+#[derive(Serialize, Debug, Clone)]
+pub struct ByteStats {
+    pub backup_size: u64,
+    pub target_size: u64,
+    pub backup_sha256: String,
+    pub target_sha256: String,
+    pub identical: bool,
+    pub differing_bytes: u64,
+    pub first_difference: Option<u64>,
+}
+
+#[derive(Serialize, Debug, Clone)]
+pub struct BackupDiff {
+    pub backup_path: String,
+    pub target_path: String,
+    pub bytes: ByteStats,
+    pub change_count: usize,
+    /// Changes a restore would make: old values are the target's, new values the backup's.
+    pub sections: Vec<SectionDiff>,
+}
+
+fn byte_stats(backup: &[u8], target: &[u8]) -> ByteStats {
+    let common = backup.len().min(target.len());
+    let mismatched = (0..common).filter(|&i| backup[i] != target[i]);
+    let first_difference = mismatched
+        .clone()
+        .next()
+        .or((backup.len() != target.len()).then_some(common))
+        .map(|i| i as u64);
+    let differing_bytes = mismatched.count() as u64 + backup.len().abs_diff(target.len()) as u64;
+
+    ByteStats {
+        backup_size: backup.len() as u64,
+        target_size: target.len() as u64,
+        backup_sha256: sha256_of_bytes(backup),
+        target_sha256: sha256_of_bytes(target),
+        identical: backup == target,
+        differing_bytes,
+        first_difference,
+    }
+}
+
+/// Path of the live core_*.dat a backup was taken from.
+fn live_path_for_backup(backup: &Path, metadata: &BackupMetadata) -> AppResult<PathBuf> {
+    let profile_dir = location::profile_dir_for_backup(backup, metadata.source_path.as_deref())
+        .ok_or_else(|| {
+            AppError::invalid_data("Could not determine profile directory").with_path(backup)
+        })?;
+
+    Ok(location::settings_path(
+        &profile_dir,
+        metadata.kind,
+        &metadata.original_id,
+    ))
+}
+
+#[tauri::command]
+pub fn diff_backup(backup_path: String, target_path: Option<String>) -> AppResult<BackupDiff> {
+    let backup = PathBuf::from(&backup_path);
+    let metadata = backup_metadata(&backup).ok_or_else(|| {
+        AppError::invalid_input("Unrecognized backup filename").with_path(&backup)
+    })?;
+    let backup_kind = metadata.kind;
+
+    let target = match target_path {
+        Some(p) => PathBuf::from(p),
+        None => live_path_for_backup(&backup, &metadata)?,
+    };
+
+    if !target.exists() {
+        return Err(AppError::not_found(format!(
+            "Target settings file not found: {}",
+            target.display()
+        ))
+        .with_path(&target));
+    }
+
+    let target_kind = target
+        .file_name()
+        .and_then(|n| n.to_str())
+        .and_then(parse_settings_filename)
+        .map(|(kind, _)| kind);
+    if target_kind.is_some_and(|kind| kind != backup_kind) {
+        return Err(AppError::invalid_input(
+            "Backup and target are different settings types",
+        ));
+    }
+
+    let backup_data = store::read_contents(&backup)?;
+    let target_data = fs::read(&target).at("read", &target)?;
+
+    let backup_doc = decode_settings(&backup, &backup_data)?;
+    let target_doc = decode_settings(&target, &target_data)?;
+
+    let sections = diff::diff_trees(&target_doc.root, &backup_doc.root);
+    let change_count = sections.iter().map(|s| s.changes.len()).sum();
+
+    Ok(BackupDiff {
+        backup_path,
+        target_path: target.to_string_lossy().into_owned(),
+        bytes: byte_stats(&backup_data, &target_data),
+        change_count,
+        sections,
+    })
+}
+
 // ── Export / Import ──────────────────────────────────────────────────────
 
 #[derive(Serialize, Deserialize, Debug, Clone)]
@@ -833,28 +1274,42 @@ pub struct ImportAnalysis {
     pub total_files: usize,
 }
 
+/// Outcome for one target of a write to several settings files. `error` is `None` when
+/// the target was updated or already matched.
+#[derive(Serialize, Debug, Clone)]
+pub struct TargetResult {
+    pub path: String,
+    pub error: Option<AppError>,
+}
+
 #[derive(Serialize, Debug, Clone)]
 pub struct ImportResultInfo {
     pub imported_count: usize,
     pub skipped_count: usize,
     pub backed_up_count: usize,
+    /// Files that could not be imported; the rest of the archive still is.
+    pub failed: Vec<TargetResult>,
 }
 
-fn sha256_of_bytes(data: &[u8]) -> String {
+pub(crate) fn sha256_of_bytes(data: &[u8]) -> String {
     let mut hasher = Sha256::new();
     hasher.update(data);
     format!("{:x}", hasher.finalize())
 }
 
-fn sha256_of_file(path: &Path) -> Result<String, String> {
-    let data = fs::read(path).map_err(|e| format!("Failed to read {}: {}", path.display(), e))?;
+pub(crate) fn sha256_of_file(path: &Path) -> AppResult<String> {
+    // Backup references already know the hash of the content they point at
+    if let Some(sha256) = store::recorded_sha256(path) {
+        return Ok(sha256);
+    }
+    let data = fs::read(path).at("read", path)?;
     Ok(sha256_of_bytes(&data))
 }
 
-fn collect_exportable_files(eve_root: &Path) -> Result<Vec<(PathBuf, String)>, String> {
+fn collect_exportable_files(eve_root: &Path) -> AppResult<Vec<(PathBuf, String)>> {
     let mut files: Vec<(PathBuf, String)> = Vec::new();
 
-    let server_dirs = fs::read_dir(eve_root).map_err(|e| e.to_string())?;
+    let server_dirs = fs::read_dir(eve_root).at("read", eve_root)?;
 
     for server_entry in server_dirs.flatten() {
         let server_path = server_entry.path();
@@ -890,14 +1345,14 @@ fn collect_exportable_files(eve_root: &Path) -> Result<Vec<(PathBuf, String)>, S
                         if fname.ends_with(".dat") && fname.starts_with("core_") {
                             let rel = p
                                 .strip_prefix(eve_root)
-                                .map_err(|e| e.to_string())?
+                                .map_err(|e| AppError::other(e.to_string()).with_path(&p))?
                                 .to_string_lossy()
                                 .into_owned();
                             files.push((p, rel));
                         } else if fname == "prefs.ini" {
                             let rel = p
                                 .strip_prefix(eve_root)
-                                .map_err(|e| e.to_string())?
+                                .map_err(|e| AppError::other(e.to_string()).with_path(&p))?
                                 .to_string_lossy()
                                 .into_owned();
                             files.push((p, rel));
@@ -907,62 +1362,91 @@ fn collect_exportable_files(eve_root: &Path) -> Result<Vec<(PathBuf, String)>, S
             }
 
             // Collect backup files
-            let backup_dir = profile_path.join("backups");
-            if backup_dir.is_dir() {
-                if let Ok(entries) = fs::read_dir(&backup_dir) {
-                    for entry in entries.flatten() {
-                        let p = entry.path();
-                        if p.is_file() {
-                            let fname = p.file_name().and_then(|n| n.to_str()).unwrap_or("");
-                            if fname.ends_with(".bak") {
-                                let rel = p
-                                    .strip_prefix(eve_root)
-                                    .map_err(|e| e.to_string())?
-                                    .to_string_lossy()
-                                    .into_owned();
-                                files.push((p, rel));
-                            }
-                        }
-                    }
-                }
-            }
+            collect_backup_files(&profile_path.join("backups"), eve_root, &mut files)?;
+        }
+    }
+
+    // Backups kept outside the EVE folder are exported under the same relative paths,
+    // so importing puts them next to the settings files they belong to
+    if let Some(root) = location::backup_root() {
+        for backup_dir in location::external_backup_dirs() {
+            collect_backup_files(&backup_dir, &root, &mut files)?;
         }
     }
 
     Ok(files)
 }
 
+fn collect_backup_files(
+    backup_dir: &Path,
+    base: &Path,
+    files: &mut Vec<(PathBuf, String)>,
+) -> AppResult<()> {
+    let Ok(entries) = fs::read_dir(backup_dir) else {
+        return Ok(());
+    };
+
+    for entry in entries.flatten() {
+        let p = entry.path();
+        if p.is_file() {
+            let fname = p.file_name().and_then(|n| n.to_str()).unwrap_or("");
+            if fname.ends_with(".bak") || fname.ends_with(".json") {
+                let rel = p
+                    .strip_prefix(base)
+                    .map_err(|e| AppError::other(e.to_string()).with_path(&p))?
+                    .to_string_lossy()
+                    .into_owned();
+                files.push((p, rel));
+            }
+        }
+    }
+    Ok(())
+}
+
 #[tauri::command]
 pub fn export_settings(
     app: tauri::AppHandle,
     custom_eve_path: Option<String>,
     export_path: String,
-) -> Result<ExportResult, String> {
-    let eve_root =
-        eve_settings_root(custom_eve_path.as_deref()).ok_or("EVE settings directory not found")?;
+) -> AppResult<ExportResult> {
+    export_settings_archive(&app_data_dir(&app)?, custom_eve_path, export_path)
+}
+
+fn zip_error(action: &str, archive: &Path, err: impl std::fmt::Display) -> AppError {
+    AppError::new(ErrorCode::Io, format!("Failed to {}: {}", action, err)).with_path(archive)
+}
+
+pub(crate) fn export_settings_archive(
+    data_dir: &Path,
+    custom_eve_path: Option<String>,
+    export_path: String,
+) -> AppResult<ExportResult> {
+    let eve_root = eve_settings_root(custom_eve_path.as_deref())
+        .ok_or_else(|| AppError::not_found("EVE settings directory not found"))?;
 
     if !eve_root.exists() {
-        return Err("EVE settings directory does not exist".into());
+        return Err(
+            AppError::not_found("EVE settings directory does not exist").with_path(&eve_root)
+        );
     }
 
     let exportable_files = collect_exportable_files(&eve_root)?;
 
     let dest = PathBuf::from(&export_path);
-    let file = fs::File::create(&dest).map_err(|e| format!("Failed to create zip: {}", e))?;
+    let file = fs::File::create(&dest).at("create", &dest)?;
     let mut zip = zip::ZipWriter::new(file);
     let options = SimpleFileOptions::default().compression_method(zip::CompressionMethod::Deflated);
 
     let mut manifest_files: Vec<ManifestFileEntry> = Vec::new();
 
     for (abs_path, rel_path) in &exportable_files {
-        let data = fs::read(abs_path)
-            .map_err(|e| format!("Failed to read {}: {}", abs_path.display(), e))?;
+        let data = store::read_contents(abs_path)?;
         let checksum = sha256_of_bytes(&data);
 
         zip.start_file(rel_path, options)
-            .map_err(|e| format!("Failed to add to zip: {}", e))?;
+            .map_err(|e| zip_error("add to zip", &dest, e))?;
         zip.write_all(&data)
-            .map_err(|e| format!("Failed to write to zip: {}", e))?;
+            .map_err(|e| zip_error("write to zip", &dest, e))?;
 
         manifest_files.push(ManifestFileEntry {
             relative_path: rel_path.clone(),
@@ -971,15 +1455,15 @@ pub fn export_settings(
     }
 
     // Add aliases.json if it exists
-    let aliases_path = aliases_file(&app)?;
+    let aliases_path = aliases_file(data_dir);
     if aliases_path.exists() {
-        let data = fs::read(&aliases_path).map_err(|e| format!("Failed to read aliases: {}", e))?;
+        let data = fs::read(&aliases_path).at("read", &aliases_path)?;
         let checksum = sha256_of_bytes(&data);
 
         zip.start_file("aliases.json", options)
-            .map_err(|e| format!("Failed to add aliases to zip: {}", e))?;
+            .map_err(|e| zip_error("add aliases to zip", &dest, e))?;
         zip.write_all(&data)
-            .map_err(|e| format!("Failed to write aliases to zip: {}", e))?;
+            .map_err(|e| zip_error("write aliases to zip", &dest, e))?;
 
         manifest_files.push(ManifestFileEntry {
             relative_path: "aliases.json".to_string(),
@@ -1000,15 +1484,16 @@ pub fn export_settings(
         files: manifest_files,
     };
 
-    let manifest_json = serde_json::to_string_pretty(&manifest).map_err(|e| e.to_string())?;
+    let manifest_json =
+        serde_json::to_string_pretty(&manifest).map_err(|e| AppError::other(e.to_string()))?;
 
     zip.start_file("manifest.json", options)
-        .map_err(|e| format!("Failed to add manifest: {}", e))?;
+        .map_err(|e| zip_error("add manifest", &dest, e))?;
     zip.write_all(manifest_json.as_bytes())
-        .map_err(|e| format!("Failed to write manifest: {}", e))?;
+        .map_err(|e| zip_error("write manifest", &dest, e))?;
 
     zip.finish()
-        .map_err(|e| format!("Failed to finalize zip: {}", e))?;
+        .map_err(|e| zip_error("finalize zip", &dest, e))?;
 
     let file_count = manifest.files.len();
     Ok(ExportResult {
@@ -1022,31 +1507,64 @@ pub fn analyze_import(
     app: tauri::AppHandle,
     import_path: String,
     custom_eve_path: Option<String>,
-) -> Result<ImportAnalysis, String> {
-    let eve_root =
-        eve_settings_root(custom_eve_path.as_deref()).ok_or("EVE settings directory not found")?;
+) -> AppResult<ImportAnalysis> {
+    analyze_import_archive(&app_data_dir(&app)?, import_path, custom_eve_path)
+}
 
-    let file = fs::File::open(&import_path).map_err(|e| format!("Failed to open zip: {}", e))?;
-    let mut archive = ZipArchive::new(file).map_err(|e| format!("Invalid zip file: {}", e))?;
+/// Opens an export archive and reads its manifest. Every path in the manifest must stay
+/// inside the folder it is imported into.
+fn open_archive(path: &Path) -> AppResult<(ZipArchive<fs::File>, ExportManifest)> {
+    let file = fs::File::open(path).at("open", path)?;
+    let mut archive = ZipArchive::new(file)
+        .map_err(|e| AppError::invalid_data(format!("Invalid zip file: {}", e)).with_path(path))?;
 
-    // Read manifest
     let manifest: ExportManifest = {
-        let mut manifest_file = archive
-            .by_name("manifest.json")
-            .map_err(|_| "No manifest.json found in archive - not a valid EVE Wrench export")?;
+        let mut manifest_file = archive.by_name("manifest.json").map_err(|_| {
+            AppError::invalid_data(
+                "No manifest.json found in archive - not a valid EVE Wrench export",
+            )
+            .with_path(path)
+        })?;
         let mut content = String::new();
         manifest_file
             .read_to_string(&mut content)
-            .map_err(|e| format!("Failed to read manifest: {}", e))?;
-        serde_json::from_str(&content).map_err(|e| format!("Invalid manifest: {}", e))?
+            .map_err(|e| zip_error("read manifest", path, e))?;
+        serde_json::from_str(&content).map_err(|e| {
+            AppError::invalid_data(format!("Invalid manifest: {}", e)).with_path(path)
+        })?
     };
 
+    let escaping = manifest.files.iter().find(|entry| {
+        let rel = Path::new(&entry.relative_path);
+        rel.as_os_str().is_empty() || !rel.components().all(|c| matches!(c, Component::Normal(_)))
+    });
+    if let Some(entry) = escaping {
+        return Err(AppError::invalid_data(format!(
+            "Archive entry {} is not a relative path inside the settings folder",
+            entry.relative_path
+        ))
+        .with_path(path));
+    }
+
+    Ok((archive, manifest))
+}
+
+pub(crate) fn analyze_import_archive(
+    data_dir: &Path,
+    import_path: String,
+    custom_eve_path: Option<String>,
+) -> AppResult<ImportAnalysis> {
+    let eve_root = eve_settings_root(custom_eve_path.as_deref())
+        .ok_or_else(|| AppError::not_found("EVE settings directory not found"))?;
+
+    let (_, manifest) = open_archive(Path::new(&import_path))?;
+
     let mut new_files: Vec<ImportFileInfo> = Vec::new();
     let mut conflicts: Vec<ImportConflictInfo> = Vec::new();
     let mut unchanged: Vec<ImportFileInfo> = Vec::new();
     let mut aliases_conflict = false;
 
-    let aliases_path = aliases_file(&app)?;
+    let aliases_path = aliases_file(data_dir);
 
     for entry in &manifest.files {
         let rel = &entry.relative_path;
@@ -1124,107 +1642,132 @@ pub fn execute_import(
     import_path: String,
     custom_eve_path: Option<String>,
     overwrite_paths: Vec<String>,
-) -> Result<ImportResultInfo, String> {
-    let eve_root =
-        eve_settings_root(custom_eve_path.as_deref()).ok_or("EVE settings directory not found")?;
-
-    let file = fs::File::open(&import_path).map_err(|e| format!("Failed to open zip: {}", e))?;
-    let mut archive = ZipArchive::new(file).map_err(|e| format!("Invalid zip file: {}", e))?;
+) -> AppResult<ImportResultInfo> {
+    let result = import_archive(
+        &app_data_dir(&app)?,
+        import_path,
+        custom_eve_path,
+        overwrite_paths,
+    )?;
+    emit_data_changed(&app);
+    Ok(result)
+}
 
-    // Read manifest
-    let manifest: ExportManifest = {
-        let mut manifest_file = archive
-            .by_name("manifest.json")
-            .map_err(|_| "No manifest.json found in archive")?;
-        let mut content = String::new();
-        manifest_file
-            .read_to_string(&mut content)
-            .map_err(|e| format!("Failed to read manifest: {}", e))?;
-        serde_json::from_str(&content).map_err(|e| format!("Invalid manifest: {}", e))?
+pub(crate) fn import_archive(
+    data_dir: &Path,
+    import_path: String,
+    custom_eve_path: Option<String>,
+    overwrite_paths: Vec<String>,
+) -> AppResult<ImportResultInfo> {
+    let eve_root = eve_settings_root(custom_eve_path.as_deref())
+        .ok_or_else(|| AppError::not_found("EVE settings directory not found"))?;
+
+    let (mut archive, manifest) = open_archive(Path::new(&import_path))?;
+
+    clients::ensure_writable(
+        manifest
+            .files
+            .iter()
+            .map(|entry| eve_root.join(&entry.relative_path)),
+    )?;
+
+    let aliases_path = aliases_file(data_dir);
+    let mut recorder = OperationRecorder::begin(
+        data_dir,
+        "execute_import",
+        serde_json::json!({ "import_path": import_path, "overwrite_paths": overwrite_paths }),
+    )?;
+    let mut result = ImportResultInfo {
+        imported_count: 0,
+        skipped_count: 0,
+        backed_up_count: 0,
+        failed: Vec::new(),
     };
 
-    let aliases_path = aliases_file(&app)?;
-    let mut imported_count = 0usize;
-    let mut skipped_count = 0usize;
-    let mut backed_up_count = 0usize;
-
-    let timestamp = SystemTime::now()
-        .duration_since(UNIX_EPOCH)
-        .map(|d| d.as_secs())
-        .unwrap_or(0);
-
     let overwrite_set: std::collections::HashSet<&str> =
         overwrite_paths.iter().map(|s| s.as_str()).collect();
 
     for entry in &manifest.files {
         let rel = &entry.relative_path;
-
-        // Read file data from archive
-        let mut zip_file = match archive.by_name(rel) {
-            Ok(f) => f,
-            Err(_) => {
-                skipped_count += 1;
-                continue;
-            }
-        };
-        let mut data = Vec::new();
-        zip_file
-            .read_to_end(&mut data)
-            .map_err(|e| format!("Failed to read {} from archive: {}", rel, e))?;
-
         let target_path = if rel == "aliases.json" {
             aliases_path.clone()
         } else {
             eve_root.join(rel)
         };
 
-        // Check if this is a conflict
-        if target_path.exists() {
-            let local_checksum = sha256_of_file(&target_path)?;
-            if local_checksum == entry.sha256 {
-                // Identical, skip
-                skipped_count += 1;
-                continue;
+        let imported = import_entry(
+            &mut archive,
+            Path::new(&import_path),
+            entry,
+            &target_path,
+            overwrite_set.contains(rel.as_str()),
+            &mut recorder,
+        );
+        match imported {
+            Ok(ImportOutcome::Skipped) => result.skipped_count += 1,
+            Ok(ImportOutcome::Written { backed_up }) => {
+                result.imported_count += 1;
+                result.backed_up_count += usize::from(backed_up);
             }
+            Err(e) => result.failed.push(TargetResult {
+                path: target_path.to_string_lossy().into_owned(),
+                error: Some(e),
+            }),
+        }
+    }
 
-            if !overwrite_set.contains(rel.as_str()) {
-                skipped_count += 1;
-                continue;
-            }
+    Ok(result)
+}
 
-            // Back up existing file before overwriting
-            if rel != "aliases.json" {
-                if let Some(parent) = target_path.parent() {
-                    let backup_dir = parent.join("backups");
-                    let _ = fs::create_dir_all(&backup_dir);
-
-                    if let Some(fname) = target_path.file_name().and_then(|n| n.to_str()) {
-                        let backup_name = format!("pre_import_{}_{}", fname, timestamp);
-                        let backup_path = backup_dir.join(&backup_name);
-                        if fs::copy(&target_path, &backup_path).is_ok() {
-                            backed_up_count += 1;
-                        }
-                    }
-                }
-            }
-        }
+enum ImportOutcome {
+    Skipped,
+    Written { backed_up: bool },
+}
 
-        // Ensure parent directory exists
-        if let Some(parent) = target_path.parent() {
-            fs::create_dir_all(parent).map_err(|e| format!("Failed to create directory: {}", e))?;
+/// Writes one archive entry to `target_path`, unless the file there already matches or
+/// is not to be overwritten.
+fn import_entry(
+    archive: &mut ZipArchive<fs::File>,
+    archive_path: &Path,
+    entry: &ManifestFileEntry,
+    target_path: &Path,
+    overwrite: bool,
+    recorder: &mut OperationRecorder,
+) -> AppResult<ImportOutcome> {
+    let rel = &entry.relative_path;
+    let mut zip_file = archive.by_name(rel).map_err(|e| {
+        AppError::not_found(format!("{} is missing from the archive: {}", rel, e))
+            .with_path(archive_path)
+    })?;
+    let mut data = Vec::new();
+    zip_file
+        .read_to_end(&mut data)
+        .map_err(|e| zip_error(&format!("read {} from archive", rel), archive_path, e))?;
+
+    let mut backed_up = false;
+    if target_path.exists() {
+        if sha256_of_file(target_path)? == entry.sha256 || !overwrite {
+            return Ok(ImportOutcome::Skipped);
         }
 
-        // Write the file
-        fs::write(&target_path, &data).map_err(|e| format!("Failed to write {}: {}", rel, e))?;
-
-        imported_count += 1;
+        // Settings files get a regular backup; backups, sidecars and aliases being
+        // replaced are only kept in the journal
+        let is_settings = target_path
+            .file_name()
+            .and_then(|n| n.to_str())
+            .and_then(parse_settings_filename)
+            .is_some();
+        if is_settings {
+            recorder.back_up(target_path, "pre_import")?;
+            backed_up = true;
+        }
     }
 
-    emit_data_changed(&app);
-
-    Ok(ImportResultInfo {
-        imported_count,
-        skipped_count,
-        backed_up_count,
-    })
+    if let Some(parent) = target_path.parent() {
+        fs::create_dir_all(parent).at("create", parent)?;
+    }
+    // A no-op for settings files backed up above
+    recorder.snapshot(target_path)?;
+    fs::write(target_path, &data).at("write", target_path)?;
+    Ok(ImportOutcome::Written { backed_up })
 }
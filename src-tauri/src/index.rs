@@ -0,0 +1,85 @@
+use crate::evesettings::{backup_entry, backup_metadata, BackupEntry};
+use crate::metadata::{self, BackupMetadata};
+use crate::prefs;
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::{LazyLock, Mutex, MutexGuard, PoisonError};
+use std::time::SystemTime;
+
+/// Memo cache of what earlier scans read, keyed by path and valid while the file keeps
+/// its modification time. A scan still lists every folder and stats every file it looks
+/// up; what the cache saves is reading and parsing the ones that did not change. It is
+/// not fed by the file watcher. Settings files need no entry: the stat that would
+/// validate one is all a scan does with them. Entries for files a scan no longer finds
+/// are evicted at the end of that scan.
+#[derive(Default)]
+struct ScanIndex {
+    /// Backup metadata, checked against the backup and its sidecar.
+    backups: HashMap<PathBuf, ((Stamp, Stamp), BackupMetadata)>,
+    /// Flags read from a profile's prefs.ini, keyed by file and flag name.
+    prefs_flags: HashMap<(PathBuf, &'static str), (Stamp, bool)>,
+}
+
+type Stamp = Option<SystemTime>;
+
+static INDEX: LazyLock<Mutex<ScanIndex>> = LazyLock::new(|| Mutex::new(ScanIndex::default()));
+
+fn index() -> MutexGuard<'static, ScanIndex> {
+    INDEX.lock().unwrap_or_else(PoisonError::into_inner)
+}
+
+fn stamp(path: &Path) -> Stamp {
+    fs::metadata(path).and_then(|m| m.modified()).ok()
+}
+
+/// `backup_entry` for the backup at `path`. Its metadata is read again only when the
+/// backup or its sidecar changed; the entry itself is built fresh, since its relative
+/// time moves on regardless.
+pub(crate) fn backup(path: &Path) -> Option<BackupEntry> {
+    let stamps = (stamp(path), stamp(&metadata::sidecar_path(path)));
+    let cached = index()
+        .backups
+        .get(path)
+        .filter(|(cached, _)| *cached == stamps)
+        .map(|(_, metadata)| metadata.clone());
+
+    let metadata = match cached {
+        Some(metadata) => metadata,
+        None => {
+            let metadata = backup_metadata(path)?;
+            index()
+                .backups
+                .insert(path.to_path_buf(), (stamps, metadata.clone()));
+            metadata
+        }
+    };
+    Some(backup_entry(path, metadata))
+}
+
+/// Drops backups a full scan no longer found.
+pub(crate) fn retain_backups(found: &HashSet<PathBuf>) {
+    index().backups.retain(|path, _| found.contains(path));
+}
+
+/// Drops flags of profiles a full scan no longer found.
+pub(crate) fn retain_profiles(found: &HashSet<PathBuf>) {
+    index()
+        .prefs_flags
+        .retain(|(file, _), _| file.parent().is_some_and(|dir| found.contains(dir)));
+}
+
+/// `prefs::profile_flag`, read again only when the profile's prefs.ini changed.
+pub(crate) fn profile_flag(profile_dir: &Path, name: &'static str) -> bool {
+    let key = (profile_dir.join(prefs::PREFS_FILE), name);
+    let current = stamp(&key.0);
+    if let Some((cached, value)) = index().prefs_flags.get(&key) {
+        if *cached == current {
+            return *value;
+        }
+    }
+
+    let value = prefs::profile_flag(profile_dir, name);
+    index().prefs_flags.insert(key, (current, value));
+    value
+}
@@ -1,12 +1,51 @@
+mod cli;
+mod clients;
+mod diff;
+mod error;
 mod esi;
 mod evesettings;
+mod index;
+mod location;
+mod marshal;
+mod metadata;
+mod operations;
+mod overview;
+mod prefs;
+mod restore;
+mod retention;
+mod scheduler;
+mod sections;
+mod shortcuts;
+mod store;
 mod updates;
+mod verify;
+mod watcher;
+mod windows;
 
+use clients::{
+    cancel_queued_operation, get_queued_operations, get_running_clients, queue_operation,
+};
 use evesettings::{
-    analyze_import, copy_settings, create_backup, delete_backup, execute_import, export_settings,
-    get_app_data, set_alias, set_brackets_always_show,
+    analyze_import, copy_settings, copy_settings_sections, create_backup, delete_backup,
+    diff_backup, diff_settings, execute_import, export_settings, get_app_data,
+    get_settings_sections, read_settings_tree, set_alias, write_settings_tree,
+};
+use location::{get_backup_root, move_backups_to_root, set_backup_root};
+use metadata::{search_backups, update_backup_metadata};
+use operations::{list_operations, undo_operation};
+use overview::{export_overview, import_overview};
+use prefs::{get_prefs, set_pref};
+use restore::restore_backup;
+use retention::{get_retention_policy, prune_backups, set_retention_policy};
+use scheduler::{
+    get_backup_schedule, get_last_scheduled_run, run_scheduled_backup, set_backup_schedule,
 };
+use shortcuts::{check_shortcuts, export_shortcuts, import_shortcuts};
+use store::migrate_backup_store;
 use updates::check_for_update;
+use verify::verify_backups;
+use watcher::watch_settings;
+use windows::{apply_window_layout, get_window_layout};
 
 #[cfg_attr(mobile, tauri::mobile_entry_point)]
 pub fn run() {
@@ -15,18 +54,68 @@ pub fn run() {
         .plugin(tauri_plugin_os::init())
         .plugin(tauri_plugin_dialog::init())
         .plugin(tauri_plugin_store::Builder::new().build())
+        .setup(|app| {
+            if let Ok(data_dir) = evesettings::app_data_dir(app.handle()) {
+                location::init(&data_dir);
+            }
+            scheduler::init(app.handle());
+            clients::init(app.handle());
+            watcher::init(app.handle());
+            Ok(())
+        })
         .invoke_handler(tauri::generate_handler![
             get_app_data,
             create_backup,
             delete_backup,
             copy_settings,
             set_alias,
-            set_brackets_always_show,
             check_for_update,
             export_settings,
             analyze_import,
             execute_import,
+            read_settings_tree,
+            write_settings_tree,
+            get_settings_sections,
+            copy_settings_sections,
+            diff_settings,
+            diff_backup,
+            export_overview,
+            import_overview,
+            export_shortcuts,
+            import_shortcuts,
+            check_shortcuts,
+            get_window_layout,
+            apply_window_layout,
+            get_prefs,
+            set_pref,
+            get_retention_policy,
+            set_retention_policy,
+            prune_backups,
+            get_backup_schedule,
+            set_backup_schedule,
+            run_scheduled_backup,
+            get_last_scheduled_run,
+            list_operations,
+            undo_operation,
+            migrate_backup_store,
+            restore_backup,
+            update_backup_metadata,
+            search_backups,
+            get_backup_root,
+            set_backup_root,
+            move_backups_to_root,
+            verify_backups,
+            get_running_clients,
+            queue_operation,
+            get_queued_operations,
+            cancel_queued_operation,
+            watch_settings,
         ])
         .run(tauri::generate_context!())
         .expect("error while running tauri application");
 }
+
+/// Runs the headless command-line interface instead of the app.
+pub fn run_cli() {
+    cli::run()
+}
@@ -0,0 +1,247 @@
+use crate::error::{AppError, AppResult, ErrorCode, IoResultExt};
+use crate::evesettings::{
+    app_data_dir, backup_metadata, emit_data_changed, scan_backups, sha256_of_bytes, SettingsKind,
+};
+use crate::metadata;
+use crate::operations::OperationRecorder;
+use crate::store;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::RwLock;
+
+// With a backup root configured, backups live under
+// <root>/<server folder>/settings_<profile>/backups instead of next to the settings
+// files, mirroring the EVE layout so the content store and everything that derives a
+// backup's server and profile from its path work the same in both places.
+static BACKUP_ROOT: RwLock<Option<PathBuf>> = RwLock::new(None);
+
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+struct LocationConfig {
+    backup_root: Option<String>,
+}
+
+#[derive(Serialize, Debug, Clone, Default)]
+pub struct BackupMoveResult {
+    pub moved_count: usize,
+    pub blobs_removed: usize,
+    pub errors: Vec<String>,
+}
+
+fn config_file(data_dir: &Path) -> PathBuf {
+    data_dir.join("backup_location.json")
+}
+
+fn load_config(data_dir: &Path) -> LocationConfig {
+    fs::read_to_string(config_file(data_dir))
+        .ok()
+        .and_then(|content| serde_json::from_str(&content).ok())
+        .unwrap_or_default()
+}
+
+/// Loads the configured backup root.
+pub(crate) fn init(data_dir: &Path) {
+    *BACKUP_ROOT.write().unwrap() = load_config(data_dir).backup_root.map(PathBuf::from);
+}
+
+pub(crate) fn backup_root() -> Option<PathBuf> {
+    BACKUP_ROOT.read().unwrap().clone()
+}
+
+fn is_external(backup: &Path) -> bool {
+    backup_root().is_some_and(|root| backup.starts_with(root))
+}
+
+/// Folder new backups of `source` go into.
+pub(crate) fn backup_dir_for(source: &Path) -> AppResult<PathBuf> {
+    let profile_dir = source
+        .parent() // settings_profile dir (e.g., settings_Default)
+        .ok_or_else(|| {
+            AppError::invalid_input("Could not determine profile directory").with_path(source)
+        })?;
+
+    let Some(root) = backup_root() else {
+        return Ok(profile_dir.join("backups"));
+    };
+
+    let profile = profile_dir.file_name().ok_or_else(|| {
+        AppError::invalid_input("Could not determine profile directory").with_path(source)
+    })?;
+    let server = profile_dir
+        .parent()
+        .and_then(|p| p.file_name())
+        .ok_or_else(|| {
+            AppError::invalid_input("Could not determine server directory").with_path(source)
+        })?;
+    Ok(root.join(server).join(profile).join("backups"))
+}
+
+/// Every backups folder under the configured root.
+pub(crate) fn external_backup_dirs() -> Vec<PathBuf> {
+    let Some(root) = backup_root() else {
+        return Vec::new();
+    };
+
+    let mut dirs = Vec::new();
+    for server in fs::read_dir(&root).into_iter().flatten().flatten() {
+        for profile in fs::read_dir(server.path()).into_iter().flatten().flatten() {
+            let is_profile = profile
+                .file_name()
+                .to_str()
+                .is_some_and(|n| n.starts_with("settings_"));
+            let backups = profile.path().join("backups");
+            if is_profile && backups.is_dir() {
+                dirs.push(backups);
+            }
+        }
+    }
+    dirs
+}
+
+/// Profile folder holding the settings file a backup was taken from. External backups
+/// only know it from their metadata.
+pub(crate) fn profile_dir_for_backup(backup: &Path, source_path: Option<&str>) -> Option<PathBuf> {
+    if let Some(source) = source_path {
+        return Path::new(source).parent().map(Path::to_path_buf);
+    }
+    if is_external(backup) {
+        return None;
+    }
+    backup.parent()?.parent().map(Path::to_path_buf)
+}
+
+pub(crate) fn settings_path(profile_dir: &Path, kind: SettingsKind, id: &str) -> PathBuf {
+    profile_dir.join(format!("core_{}_{}.dat", kind.file_tag(), id))
+}
+
+#[tauri::command]
+pub fn get_backup_root() -> Option<String> {
+    backup_root().map(|root| root.to_string_lossy().into_owned())
+}
+
+/// Sets the folder backups are kept in, or `None` to keep them next to the settings
+/// files. Existing backups stay where they are until `move_backups_to_root` is run.
+#[tauri::command]
+pub fn set_backup_root(app: tauri::AppHandle, path: Option<String>) -> AppResult<()> {
+    let root = match path.as_deref().map(str::trim) {
+        Some(p) if !p.is_empty() => {
+            let root = PathBuf::from(p);
+            if !root.is_absolute() {
+                return Err(AppError::invalid_input(
+                    "Backup folder must be an absolute path",
+                ));
+            }
+            fs::create_dir_all(&root).at("create", &root)?;
+            Some(root)
+        }
+        _ => None,
+    };
+
+    let config = LocationConfig {
+        backup_root: root.as_ref().map(|r| r.to_string_lossy().into_owned()),
+    };
+    let content =
+        serde_json::to_string_pretty(&config).map_err(|e| AppError::other(e.to_string()))?;
+    let config_path = config_file(&app_data_dir(&app)?);
+    fs::write(&config_path, content).at("write", &config_path)?;
+
+    *BACKUP_ROOT.write().unwrap() = root;
+    emit_data_changed(&app);
+    Ok(())
+}
+
+/// Moves a backup and its metadata into the configured root.
+fn move_backup(backup: &Path, recorder: &mut OperationRecorder) -> AppResult<()> {
+    let mut metadata = backup_metadata(backup).ok_or_else(|| {
+        AppError::invalid_input(format!("Not a backup file: {}", backup.display()))
+            .with_path(backup)
+    })?;
+    let profile_dir =
+        profile_dir_for_backup(backup, metadata.source_path.as_deref()).ok_or_else(|| {
+            AppError::invalid_data(format!("Unknown source for {}", backup.display()))
+                .with_path(backup)
+        })?;
+    let source = metadata
+        .source_path
+        .clone()
+        .map(PathBuf::from)
+        .unwrap_or_else(|| settings_path(&profile_dir, metadata.kind, &metadata.original_id));
+
+    let filename = backup
+        .file_name()
+        .ok_or_else(|| AppError::invalid_input("Invalid backup path").with_path(backup))?;
+    let dest_dir = backup_dir_for(&source)?;
+    let dest = dest_dir.join(filename);
+    if dest.exists() {
+        return Err(AppError::new(
+            ErrorCode::AlreadyExists,
+            format!("{} already exists", dest.display()),
+        )
+        .with_path(&dest));
+    }
+    fs::create_dir_all(&dest_dir).at("create", &dest_dir)?;
+
+    let sidecar = metadata::sidecar_path(backup);
+    for file in [&dest, &metadata::sidecar_path(&dest), backup, &sidecar] {
+        recorder.snapshot(file)?;
+    }
+
+    let data = store::read_contents(backup)?;
+    // Once moved, the source path is the only link back to the EVE folder
+    metadata.source_path = Some(source.to_string_lossy().into_owned());
+    metadata
+        .sha256
+        .get_or_insert_with(|| sha256_of_bytes(&data));
+    store::store_backup(&dest, &data)?;
+    metadata::write_metadata(&dest, &metadata)?;
+
+    if sidecar.exists() {
+        fs::remove_file(&sidecar).at("delete", &sidecar)?;
+    }
+    fs::remove_file(backup).at("delete", backup)
+}
+
+/// Moves every backup kept next to the settings files into the configured root.
+#[tauri::command]
+pub fn move_backups_to_root(
+    app: tauri::AppHandle,
+    custom_eve_path: Option<String>,
+) -> AppResult<BackupMoveResult> {
+    if backup_root().is_none() {
+        return Err(AppError::invalid_input("No backup folder configured"));
+    }
+
+    let backups = scan_backups(custom_eve_path.as_deref())?;
+    let in_tree: Vec<&str> = backups
+        .iter()
+        .map(|b| b.path.as_str())
+        .filter(|p| !is_external(Path::new(p)))
+        .collect();
+
+    let data_dir = app_data_dir(&app)?;
+    let mut recorder = OperationRecorder::begin(
+        &data_dir,
+        "move_backups_to_root",
+        serde_json::json!({ "custom_eve_path": custom_eve_path }),
+    )?;
+    let mut result = BackupMoveResult::default();
+    for path in &in_tree {
+        match move_backup(Path::new(path), &mut recorder) {
+            Ok(()) => result.moved_count += 1,
+            Err(e) => result.errors.push(e.to_string()),
+        }
+    }
+    drop(recorder);
+
+    for dir in store::store_dirs(&in_tree) {
+        match store::collect_garbage(&dir, &data_dir) {
+            Ok(removed) => result.blobs_removed += removed,
+            Err(e) => result.errors.push(e.to_string()),
+        }
+    }
+
+    if result.moved_count > 0 {
+        emit_data_changed(&app);
+    }
+    Ok(result)
+}
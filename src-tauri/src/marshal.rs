@@ -0,0 +1,1200 @@
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+
+// Blue marshal stream layout used by core_user_*.dat / core_char_*.dat:
+//
+//   0x7E | u32 saved object count | value stream | u32 slot map (one per saved object)
+//
+// Every value starts with an opcode byte. The low six bits select the type, bit 6
+// marks the value as "saved" so later Op_SavedStreamElement entries can refer back
+// to it, and bit 7 is unused by the client.
+
+const STREAM_HEADER: u8 = 0x7E;
+
+const FLAG_SAVED: u8 = 0x40;
+
+/// Deepest container nesting the decoder and encoder follow. Real settings nest a
+/// handful of levels; the limit keeps a corrupt or hostile file from overflowing the
+/// stack of any thread, including unoptimized builds on 2 MB worker threads.
+const MAX_DEPTH: usize = 100;
+const OPCODE_MASK: u8 = 0x3F;
+
+const OP_NONE: u8 = 0x01;
+const OP_TOKEN: u8 = 0x02;
+const OP_LONG_LONG: u8 = 0x03;
+const OP_LONG: u8 = 0x04;
+const OP_SIGNED_SHORT: u8 = 0x05;
+const OP_BYTE: u8 = 0x06;
+const OP_MINUS_ONE: u8 = 0x07;
+const OP_ZERO: u8 = 0x08;
+const OP_ONE: u8 = 0x09;
+const OP_REAL: u8 = 0x0A;
+const OP_ZERO_REAL: u8 = 0x0B;
+const OP_BUFFER: u8 = 0x0D;
+const OP_EMPTY_STRING: u8 = 0x0E;
+const OP_CHAR_STRING: u8 = 0x0F;
+const OP_SHORT_STRING: u8 = 0x10;
+const OP_STRING_TABLE: u8 = 0x11;
+const OP_UCS2_STRING: u8 = 0x12;
+const OP_LONG_STRING: u8 = 0x13;
+const OP_TUPLE: u8 = 0x14;
+const OP_LIST: u8 = 0x15;
+const OP_DICT: u8 = 0x16;
+const OP_OBJECT: u8 = 0x17;
+const OP_SUB_STRUCT: u8 = 0x19;
+const OP_SAVED_ELEMENT: u8 = 0x1B;
+const OP_CHECKSUMMED: u8 = 0x1C;
+const OP_TRUE: u8 = 0x1F;
+const OP_FALSE: u8 = 0x20;
+const OP_PICKLE: u8 = 0x21;
+const OP_OBJECT_EX1: u8 = 0x22;
+const OP_OBJECT_EX2: u8 = 0x23;
+const OP_EMPTY_TUPLE: u8 = 0x24;
+const OP_ONE_TUPLE: u8 = 0x25;
+const OP_EMPTY_LIST: u8 = 0x26;
+const OP_ONE_LIST: u8 = 0x27;
+const OP_EMPTY_UNICODE: u8 = 0x28;
+const OP_UNICODE_BYTE: u8 = 0x29;
+const OP_PACKED_ROW: u8 = 0x2A;
+const OP_SUB_STREAM: u8 = 0x2B;
+const OP_TWO_TUPLE: u8 = 0x2C;
+const OP_TERMINATOR: u8 = 0x2D;
+const OP_UTF8_STRING: u8 = 0x2E;
+const OP_VAR_INTEGER: u8 = 0x2F;
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(tag = "type", content = "value", rename_all = "snake_case")]
+pub enum Value {
+    None,
+    Bool(bool),
+    Int(i64),
+    /// Arbitrary precision integer, little-endian two's complement.
+    Long(Vec<u8>),
+    Float(f64),
+    /// Python 2 byte string, mapped 1:1 onto U+0000..U+00FF.
+    Str(String),
+    Unicode(String),
+    Buffer(Vec<u8>),
+    /// Global name such as a class or function reference.
+    Token(String),
+    /// Index into the client's built-in marshal string table.
+    Interned(u8),
+    Tuple(Vec<Value>),
+    List(Vec<Value>),
+    Dict(Vec<(Value, Value)>),
+    Object {
+        class: Box<Value>,
+        args: Box<Value>,
+    },
+    ObjectEx {
+        reduce: bool,
+        header: Box<Value>,
+        list: Vec<Value>,
+        dict: Vec<(Value, Value)>,
+    },
+    SubStruct(Box<Value>),
+    SubStream(Vec<u8>),
+    Checksummed {
+        checksum: u32,
+        value: Box<Value>,
+    },
+    Pickle(Vec<u8>),
+    /// A value the stream stores under `slot` so it can be referenced again.
+    Shared(u32, Box<Value>),
+    /// A back-reference to a shared slot, carrying a copy of the value it points at.
+    Ref(u32, Box<Value>),
+    /// An integer the stream stored wider than it needs, with that width in bytes, so
+    /// it is written back the same way.
+    Sized(u8, Box<Value>),
+}
+
+impl Value {
+    /// Looks through `Shared`/`Ref` wrappers to the value they carry.
+    pub fn resolve(&self) -> &Value {
+        let mut value = self;
+        while let Value::Shared(_, inner) | Value::Ref(_, inner) | Value::Sized(_, inner) = value {
+            value = inner;
+        }
+        value
+    }
+
+    pub fn resolve_mut(&mut self) -> &mut Value {
+        let mut value = self;
+        loop {
+            match value {
+                Value::Shared(_, inner) | Value::Ref(_, inner) | Value::Sized(_, inner) => {
+                    value = inner;
+                }
+                other => return other,
+            }
+        }
+    }
+
+    pub fn as_str(&self) -> Option<&str> {
+        match self.resolve() {
+            Value::Str(s) | Value::Unicode(s) | Value::Token(s) => Some(s),
+            _ => None,
+        }
+    }
+
+    pub fn as_int(&self) -> Option<i64> {
+        match self.resolve() {
+            Value::Int(i) => Some(*i),
+            Value::Bool(b) => Some(*b as i64),
+            _ => None,
+        }
+    }
+
+    pub fn as_dict(&self) -> Option<&Vec<(Value, Value)>> {
+        match self.resolve() {
+            Value::Dict(entries) => Some(entries),
+            _ => None,
+        }
+    }
+
+    pub fn as_dict_mut(&mut self) -> Option<&mut Vec<(Value, Value)>> {
+        match self.resolve_mut() {
+            Value::Dict(entries) => Some(entries),
+            _ => None,
+        }
+    }
+
+    /// Returns a copy with all `Shared`/`Ref`/`Sized` wrappers removed, suitable for
+    /// comparing values and for moving them into another stream whose saved object
+    /// slots are unrelated. Recursion is bounded by the tree: decoded trees are at most
+    /// `MAX_DEPTH` deep plus the copies refs carry, and trees from the frontend pass
+    /// through serde_json's own depth limit.
+    pub fn detached(&self) -> Value {
+        match self {
+            Value::Shared(_, inner) | Value::Ref(_, inner) | Value::Sized(_, inner) => {
+                inner.detached()
+            }
+            other => other.map_children(&Value::detached),
+        }
+    }
+
+    /// Returns a copy without the integer widths kept for re-encoding, for showing and
+    /// editing the tree. `restore_widths` puts them back before it is written.
+    pub fn without_widths(&self) -> Value {
+        match self {
+            Value::Sized(_, inner) => inner.without_widths(),
+            other => other.map_children(&Value::without_widths),
+        }
+    }
+
+    /// Puts back the integer widths `original` recorded onto the integers of this tree
+    /// that sit in the same place: sequences are matched by position, dict entries by
+    /// key. Integers that were edited keep the old width when they still fit it.
+    pub fn restore_widths(&mut self, original: &Value) {
+        if let (Value::Int(i), Value::Sized(width, _)) = (&*self, original) {
+            *self = sized_int(*width, *i);
+            return;
+        }
+        if let Value::Shared(_, inner) | Value::Ref(_, inner) = self {
+            return inner.restore_widths(original);
+        }
+        if let Value::Shared(_, inner) | Value::Ref(_, inner) = original {
+            return self.restore_widths(inner);
+        }
+
+        match (self, original) {
+            (Value::Tuple(items), Value::Tuple(old)) | (Value::List(items), Value::List(old)) => {
+                for (item, old) in items.iter_mut().zip(old) {
+                    item.restore_widths(old);
+                }
+            }
+            (Value::Dict(entries), Value::Dict(old)) => restore_entry_widths(entries, old),
+            (
+                Value::Object { class, args },
+                Value::Object {
+                    class: old_class,
+                    args: old_args,
+                },
+            ) => {
+                class.restore_widths(old_class);
+                args.restore_widths(old_args);
+            }
+            (
+                Value::ObjectEx {
+                    header, list, dict, ..
+                },
+                Value::ObjectEx {
+                    header: old_header,
+                    list: old_list,
+                    dict: old_dict,
+                    ..
+                },
+            ) => {
+                header.restore_widths(old_header);
+                for (item, old) in list.iter_mut().zip(old_list) {
+                    item.restore_widths(old);
+                }
+                restore_entry_widths(dict, old_dict);
+            }
+            (Value::SubStruct(inner), Value::SubStruct(old))
+            | (Value::Checksummed { value: inner, .. }, Value::Checksummed { value: old, .. }) => {
+                inner.restore_widths(old);
+            }
+            _ => {}
+        }
+    }
+
+    /// Copy of the value with `f` applied to each value it directly contains.
+    fn map_children(&self, f: &dyn Fn(&Value) -> Value) -> Value {
+        let entries = |entries: &[(Value, Value)]| -> Vec<(Value, Value)> {
+            entries.iter().map(|(k, v)| (f(k), f(v))).collect()
+        };
+        match self {
+            Value::Tuple(items) => Value::Tuple(items.iter().map(f).collect()),
+            Value::List(items) => Value::List(items.iter().map(f).collect()),
+            Value::Dict(dict) => Value::Dict(entries(dict)),
+            Value::Object { class, args } => Value::Object {
+                class: Box::new(f(class)),
+                args: Box::new(f(args)),
+            },
+            Value::ObjectEx {
+                reduce,
+                header,
+                list,
+                dict,
+            } => Value::ObjectEx {
+                reduce: *reduce,
+                header: Box::new(f(header)),
+                list: list.iter().map(f).collect(),
+                dict: entries(dict),
+            },
+            Value::SubStruct(inner) => Value::SubStruct(Box::new(f(inner))),
+            Value::Checksummed { checksum, value } => Value::Checksummed {
+                checksum: *checksum,
+                value: Box::new(f(value)),
+            },
+            Value::Shared(slot, inner) => Value::Shared(*slot, Box::new(f(inner))),
+            Value::Ref(slot, inner) => Value::Ref(*slot, Box::new(f(inner))),
+            Value::Sized(width, inner) => Value::Sized(*width, Box::new(f(inner))),
+            other => other.clone(),
+        }
+    }
+}
+
+fn restore_entry_widths(entries: &mut [(Value, Value)], old: &[(Value, Value)]) {
+    for (i, (key, value)) in entries.iter_mut().enumerate() {
+        let detached = key.detached();
+        // Entries usually keep their order, so try the same position first
+        let matching = old
+            .get(i)
+            .filter(|(k, _)| k.detached() == detached)
+            .or_else(|| old.iter().find(|(k, _)| k.detached() == detached));
+        if let Some((old_key, old_value)) = matching {
+            key.restore_widths(old_key);
+            value.restore_widths(old_value);
+        }
+    }
+}
+
+/// Opcode choices that vary between client builds and cannot be derived from the
+/// value alone. Recorded while decoding so the encoder writes the same flavour back.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct Dialect {
+    pub short_strings: bool,
+    pub unicode_byte: bool,
+    pub ucs2_unicode: bool,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Document {
+    pub root: Value,
+    pub dialect: Dialect,
+}
+
+pub fn decode(data: &[u8]) -> Result<Document, String> {
+    if data.first() != Some(&STREAM_HEADER) {
+        return Err("Not a marshal stream (missing 0x7E header)".into());
+    }
+    if data.len() < 5 {
+        return Err("Settings data is truncated".into());
+    }
+
+    let saved_count = u32::from_le_bytes([data[1], data[2], data[3], data[4]]) as usize;
+    let map_len = saved_count
+        .checked_mul(4)
+        .filter(|len| *len <= data.len() - 5)
+        .ok_or("Saved object table is larger than the file")?;
+    let body_end = data.len() - map_len;
+
+    let slot_map = data[body_end..]
+        .chunks_exact(4)
+        .map(|c| u32::from_le_bytes([c[0], c[1], c[2], c[3]]))
+        .collect();
+
+    let mut decoder = Decoder {
+        data: &data[..body_end],
+        pos: 5,
+        slot_map,
+        next_saved: 0,
+        saved: vec![None; saved_count],
+        dialect: Dialect::default(),
+        depth: 0,
+    };
+
+    let root = decoder.read_value()?;
+
+    if decoder.pos != body_end {
+        return Err(format!(
+            "Unexpected trailing data at offset {} ({} bytes)",
+            decoder.pos,
+            body_end - decoder.pos
+        ));
+    }
+
+    Ok(Document {
+        root,
+        dialect: decoder.dialect,
+    })
+}
+
+struct Decoder<'a> {
+    data: &'a [u8],
+    pos: usize,
+    slot_map: Vec<u32>,
+    next_saved: usize,
+    saved: Vec<Option<Value>>,
+    dialect: Dialect,
+    /// Containers currently being read.
+    depth: usize,
+}
+
+impl Decoder<'_> {
+    fn read_bytes(&mut self, len: usize) -> Result<&[u8], String> {
+        let end = self
+            .pos
+            .checked_add(len)
+            .filter(|end| *end <= self.data.len())
+            .ok_or_else(|| format!("Unexpected end of settings data at offset {}", self.pos))?;
+        let bytes = &self.data[self.pos..end];
+        self.pos = end;
+        Ok(bytes)
+    }
+
+    fn read_u8(&mut self) -> Result<u8, String> {
+        Ok(self.read_bytes(1)?[0])
+    }
+
+    fn read_array<const N: usize>(&mut self) -> Result<[u8; N], String> {
+        let mut buf = [0u8; N];
+        buf.copy_from_slice(self.read_bytes(N)?);
+        Ok(buf)
+    }
+
+    fn peek_u8(&self) -> Result<u8, String> {
+        self.data
+            .get(self.pos)
+            .copied()
+            .ok_or_else(|| format!("Unexpected end of settings data at offset {}", self.pos))
+    }
+
+    fn read_size(&mut self) -> Result<usize, String> {
+        let len = self.read_u8()?;
+        if len == 0xFF {
+            Ok(u32::from_le_bytes(self.read_array()?) as usize)
+        } else {
+            Ok(len as usize)
+        }
+    }
+
+    fn read_latin1(&mut self, len: usize) -> Result<String, String> {
+        Ok(self.read_bytes(len)?.iter().map(|&b| b as char).collect())
+    }
+
+    fn read_values(&mut self, count: usize) -> Result<Vec<Value>, String> {
+        // Guard against bogus sizes before reserving: every value is at least one byte.
+        let mut items = Vec::with_capacity(count.min(self.data.len() - self.pos));
+        for _ in 0..count {
+            items.push(self.read_value()?);
+        }
+        Ok(items)
+    }
+
+    fn read_value(&mut self) -> Result<Value, String> {
+        if self.depth >= MAX_DEPTH {
+            return Err(format!("Nesting too deep at offset {}", self.pos));
+        }
+        self.depth += 1;
+        let value = self.read_element();
+        self.depth -= 1;
+        value
+    }
+
+    fn read_element(&mut self) -> Result<Value, String> {
+        let offset = self.pos;
+        let header = self.read_u8()?;
+        let opcode = header & OPCODE_MASK;
+
+        let slot = if header & FLAG_SAVED != 0 {
+            let slot = *self
+                .slot_map
+                .get(self.next_saved)
+                .ok_or_else(|| format!("Saved object at offset {} has no slot", offset))?;
+            self.next_saved += 1;
+            if slot == 0 || slot as usize > self.saved.len() {
+                return Err(format!(
+                    "Invalid saved object slot {} at offset {}",
+                    slot, offset
+                ));
+            }
+            Some(slot)
+        } else {
+            None
+        };
+
+        let value = match opcode {
+            OP_NONE => Value::None,
+            OP_TOKEN => {
+                let len = self.read_size()?;
+                Value::Token(self.read_latin1(len)?)
+            }
+            OP_LONG_LONG => sized_int(8, i64::from_le_bytes(self.read_array()?)),
+            OP_LONG => sized_int(4, i32::from_le_bytes(self.read_array()?) as i64),
+            OP_SIGNED_SHORT => sized_int(2, i16::from_le_bytes(self.read_array()?) as i64),
+            OP_BYTE => sized_int(1, self.read_u8()? as i8 as i64),
+            OP_MINUS_ONE => Value::Int(-1),
+            OP_ZERO => Value::Int(0),
+            OP_ONE => Value::Int(1),
+            OP_REAL => Value::Float(f64::from_le_bytes(self.read_array()?)),
+            OP_ZERO_REAL => Value::Float(0.0),
+            OP_BUFFER => {
+                let len = self.read_size()?;
+                Value::Buffer(self.read_bytes(len)?.to_vec())
+            }
+            OP_EMPTY_STRING => Value::Str(String::new()),
+            OP_CHAR_STRING => Value::Str(self.read_latin1(1)?),
+            OP_SHORT_STRING => {
+                self.dialect.short_strings = true;
+                let len = self.read_u8()? as usize;
+                Value::Str(self.read_latin1(len)?)
+            }
+            OP_STRING_TABLE => Value::Interned(self.read_u8()?),
+            OP_UCS2_STRING => {
+                self.dialect.ucs2_unicode = true;
+                let len = self.read_size()?;
+                let units: Vec<u16> = self
+                    .read_bytes(len.checked_mul(2).ok_or("UCS-2 string too long")?)?
+                    .chunks_exact(2)
+                    .map(|c| u16::from_le_bytes([c[0], c[1]]))
+                    .collect();
+                Value::Unicode(
+                    String::from_utf16(&units)
+                        .map_err(|_| format!("Invalid UCS-2 string at offset {}", offset))?,
+                )
+            }
+            OP_LONG_STRING => {
+                let len = self.read_size()?;
+                Value::Str(self.read_latin1(len)?)
+            }
+            OP_TUPLE => {
+                let count = self.read_size()?;
+                Value::Tuple(self.read_values(count)?)
+            }
+            OP_LIST => {
+                let count = self.read_size()?;
+                Value::List(self.read_values(count)?)
+            }
+            OP_DICT => {
+                let count = self.read_size()?;
+                let mut entries = Vec::with_capacity(count.min(self.data.len() - self.pos));
+                for _ in 0..count {
+                    // Dict entries are stored value first, then key.
+                    let value = self.read_value()?;
+                    let key = self.read_value()?;
+                    entries.push((key, value));
+                }
+                Value::Dict(entries)
+            }
+            OP_OBJECT => {
+                let class = self.read_value()?;
+                let args = self.read_value()?;
+                Value::Object {
+                    class: Box::new(class),
+                    args: Box::new(args),
+                }
+            }
+            OP_SUB_STRUCT => Value::SubStruct(Box::new(self.read_value()?)),
+            OP_SAVED_ELEMENT => {
+                let slot = self.read_size()?;
+                let value = slot
+                    .checked_sub(1)
+                    .and_then(|i| self.saved.get(i))
+                    .and_then(|v| v.clone())
+                    .ok_or_else(|| {
+                        format!(
+                            "Reference to unknown saved object {} at offset {}",
+                            slot, offset
+                        )
+                    })?;
+                Value::Ref(slot as u32, Box::new(value))
+            }
+            OP_CHECKSUMMED => {
+                let checksum = u32::from_le_bytes(self.read_array()?);
+                Value::Checksummed {
+                    checksum,
+                    value: Box::new(self.read_value()?),
+                }
+            }
+            OP_TRUE => Value::Bool(true),
+            OP_FALSE => Value::Bool(false),
+            OP_PICKLE => {
+                let len = self.read_size()?;
+                Value::Pickle(self.read_bytes(len)?.to_vec())
+            }
+            OP_OBJECT_EX1 | OP_OBJECT_EX2 => {
+                let header = self.read_value()?;
+                let mut list = Vec::new();
+                while self.peek_u8()? != OP_TERMINATOR {
+                    list.push(self.read_value()?);
+                }
+                self.pos += 1;
+                let mut dict = Vec::new();
+                while self.peek_u8()? != OP_TERMINATOR {
+                    let key = self.read_value()?;
+                    let value = self.read_value()?;
+                    dict.push((key, value));
+                }
+                self.pos += 1;
+                Value::ObjectEx {
+                    reduce: opcode == OP_OBJECT_EX2,
+                    header: Box::new(header),
+                    list,
+                    dict,
+                }
+            }
+            OP_EMPTY_TUPLE => Value::Tuple(Vec::new()),
+            OP_ONE_TUPLE => Value::Tuple(self.read_values(1)?),
+            OP_EMPTY_LIST => Value::List(Vec::new()),
+            OP_ONE_LIST => Value::List(self.read_values(1)?),
+            OP_EMPTY_UNICODE => Value::Unicode(String::new()),
+            OP_UNICODE_BYTE => {
+                self.dialect.unicode_byte = true;
+                Value::Unicode(self.read_latin1(1)?)
+            }
+            OP_SUB_STREAM => {
+                let len = self.read_size()?;
+                Value::SubStream(self.read_bytes(len)?.to_vec())
+            }
+            OP_TWO_TUPLE => Value::Tuple(self.read_values(2)?),
+            OP_UTF8_STRING => {
+                let len = self.read_size()?;
+                let bytes = self.read_bytes(len)?.to_vec();
+                Value::Unicode(
+                    String::from_utf8(bytes)
+                        .map_err(|_| format!("Invalid UTF-8 string at offset {}", offset))?,
+                )
+            }
+            OP_VAR_INTEGER => {
+                let len = self.read_size()?;
+                Value::Long(self.read_bytes(len)?.to_vec())
+            }
+            OP_PACKED_ROW => {
+                return Err(format!("Packed rows are not supported (offset {})", offset));
+            }
+            other => {
+                return Err(format!(
+                    "Unknown opcode 0x{:02X} at offset {}",
+                    other, offset
+                ));
+            }
+        };
+
+        match slot {
+            Some(slot) => {
+                self.saved[slot as usize - 1] = Some(value.clone());
+                Ok(Value::Shared(slot, Box::new(value)))
+            }
+            None => Ok(value),
+        }
+    }
+}
+
+/// Bytes the encoder spends on `i`: none for -1, 0 and 1, which have their own opcodes.
+fn int_width(i: i64) -> u8 {
+    match i {
+        -1..=1 => 0,
+        i if i8::try_from(i).is_ok() => 1,
+        i if i16::try_from(i).is_ok() => 2,
+        i if i32::try_from(i).is_ok() => 4,
+        _ => 8,
+    }
+}
+
+fn sized_int(width: u8, i: i64) -> Value {
+    if int_width(i) == width {
+        Value::Int(i)
+    } else {
+        Value::Sized(width, Box::new(Value::Int(i)))
+    }
+}
+
+pub fn encode(doc: &Document) -> Result<Vec<u8>, String> {
+    // First pass only discovers which saved slots end up in the stream. If they still
+    // form a dense 1..=n range the original numbering is kept, which is what makes an
+    // unmodified document encode byte-for-byte identically; otherwise they are
+    // renumbered in stream order.
+    let mut probe = Encoder::new(doc.dialect, None);
+    probe.write_value(&doc.root, 0)?;
+
+    let mut sorted = probe.slot_map.clone();
+    sorted.sort_unstable();
+    let dense = sorted
+        .iter()
+        .enumerate()
+        .all(|(i, slot)| *slot == i as u32 + 1);
+    let renumber = if dense {
+        None
+    } else {
+        Some(
+            probe
+                .slot_map
+                .iter()
+                .enumerate()
+                .map(|(i, slot)| (*slot, i as u32 + 1))
+                .collect(),
+        )
+    };
+
+    let mut encoder = Encoder::new(doc.dialect, renumber);
+    encoder.write_value(&doc.root, 0)?;
+
+    let mut out = Vec::with_capacity(encoder.out.len() + 5 + encoder.slot_map.len() * 4);
+    out.push(STREAM_HEADER);
+    out.extend_from_slice(&(encoder.slot_map.len() as u32).to_le_bytes());
+    out.extend_from_slice(&encoder.out);
+    for slot in &encoder.slot_map {
+        out.extend_from_slice(&slot.to_le_bytes());
+    }
+    Ok(out)
+}
+
+struct Encoder {
+    out: Vec<u8>,
+    dialect: Dialect,
+    renumber: Option<HashMap<u32, u32>>,
+    slot_map: Vec<u32>,
+    started: HashSet<u32>,
+    completed: HashSet<u32>,
+    depth: usize,
+}
+
+impl Encoder {
+    fn new(dialect: Dialect, renumber: Option<HashMap<u32, u32>>) -> Self {
+        Encoder {
+            out: Vec::new(),
+            dialect,
+            renumber,
+            slot_map: Vec::new(),
+            started: HashSet::new(),
+            completed: HashSet::new(),
+            depth: 0,
+        }
+    }
+
+    fn slot_number(&self, slot: u32) -> u32 {
+        match &self.renumber {
+            Some(map) => map.get(&slot).copied().unwrap_or(slot),
+            None => slot,
+        }
+    }
+
+    fn write_size(&mut self, len: usize) -> Result<(), String> {
+        if len < 0xFF {
+            self.out.push(len as u8);
+        } else {
+            let len = u32::try_from(len).map_err(|_| "Value too large to encode")?;
+            self.out.push(0xFF);
+            self.out.extend_from_slice(&len.to_le_bytes());
+        }
+        Ok(())
+    }
+
+    fn write_sized(&mut self, bytes: &[u8]) -> Result<(), String> {
+        self.write_size(bytes.len())?;
+        self.out.extend_from_slice(bytes);
+        Ok(())
+    }
+
+    fn write_int(&mut self, i: i64, width: u8, flags: u8) -> Result<(), String> {
+        match (width, i) {
+            (0, -1) => self.out.push(OP_MINUS_ONE | flags),
+            (0, 0) => self.out.push(OP_ZERO | flags),
+            (0, 1) => self.out.push(OP_ONE | flags),
+            (1, i) => {
+                self.out.push(OP_BYTE | flags);
+                self.out.push(i as i8 as u8);
+            }
+            (2, i) => {
+                self.out.push(OP_SIGNED_SHORT | flags);
+                self.out.extend_from_slice(&(i as i16).to_le_bytes());
+            }
+            (4, i) => {
+                self.out.push(OP_LONG | flags);
+                self.out.extend_from_slice(&(i as i32).to_le_bytes());
+            }
+            (8, i) => {
+                self.out.push(OP_LONG_LONG | flags);
+                self.out.extend_from_slice(&i.to_le_bytes());
+            }
+            (width, _) => return Err(format!("Invalid integer width {}", width)),
+        }
+        Ok(())
+    }
+
+    fn write_values(&mut self, items: &[Value]) -> Result<(), String> {
+        for item in items {
+            self.write_value(item, 0)?;
+        }
+        Ok(())
+    }
+
+    fn write_value(&mut self, value: &Value, flags: u8) -> Result<(), String> {
+        match value {
+            Value::Shared(slot, inner) => {
+                if self.completed.contains(slot) {
+                    // The same shared value appears twice; point back at the first copy.
+                    return self.write_value(&Value::Ref(*slot, inner.clone()), flags);
+                }
+                if self.started.contains(slot) {
+                    return self.write_value(&inner.detached(), flags);
+                }
+                self.started.insert(*slot);
+                self.slot_map.push(self.slot_number(*slot));
+                self.write_value(inner, FLAG_SAVED)?;
+                self.completed.insert(*slot);
+                return Ok(());
+            }
+            Value::Ref(slot, inner) => {
+                if !self.completed.contains(slot) {
+                    // The slot it pointed at is gone (or not written yet), so inline a copy.
+                    return self.write_value(&inner.detached(), flags);
+                }
+                self.out.push(OP_SAVED_ELEMENT | flags);
+                return self.write_size(self.slot_number(*slot) as usize);
+            }
+            Value::Sized(width, inner) => {
+                return match inner.resolve() {
+                    Value::Int(i) if int_width(*i) <= *width => self.write_int(*i, *width, flags),
+                    // Edited to something that no longer fits; write it as it is now
+                    _ => self.write_value(inner, flags),
+                };
+            }
+            _ => {}
+        }
+
+        if self.depth >= MAX_DEPTH {
+            return Err("Nesting too deep to encode".into());
+        }
+        self.depth += 1;
+        let written = self.write_element(value, flags);
+        self.depth -= 1;
+        written
+    }
+
+    fn write_element(&mut self, value: &Value, flags: u8) -> Result<(), String> {
+        match value {
+            Value::None => self.out.push(OP_NONE | flags),
+            Value::Bool(b) => self.out.push((if *b { OP_TRUE } else { OP_FALSE }) | flags),
+            Value::Int(i) => self.write_int(*i, int_width(*i), flags)?,
+            Value::Long(bytes) => {
+                self.out.push(OP_VAR_INTEGER | flags);
+                self.write_sized(bytes)?;
+            }
+            Value::Float(f) => {
+                if f.to_bits() == 0 {
+                    self.out.push(OP_ZERO_REAL | flags);
+                } else {
+                    self.out.push(OP_REAL | flags);
+                    self.out.extend_from_slice(&f.to_le_bytes());
+                }
+            }
+            Value::Str(s) => {
+                let bytes = latin1_bytes(s)?;
+                match bytes.len() {
+                    0 => self.out.push(OP_EMPTY_STRING | flags),
+                    1 => {
+                        self.out.push(OP_CHAR_STRING | flags);
+                        self.out.push(bytes[0]);
+                    }
+                    len if len <= 0xFF && self.dialect.short_strings => {
+                        self.out.push(OP_SHORT_STRING | flags);
+                        self.out.push(len as u8);
+                        self.out.extend_from_slice(&bytes);
+                    }
+                    _ => {
+                        self.out.push(OP_LONG_STRING | flags);
+                        self.write_sized(&bytes)?;
+                    }
+                }
+            }
+            Value::Unicode(s) => {
+                let mut chars = s.chars();
+                match (chars.next(), chars.next()) {
+                    (None, _) => self.out.push(OP_EMPTY_UNICODE | flags),
+                    (Some(c), None) if self.dialect.unicode_byte && (c as u32) <= 0xFF => {
+                        self.out.push(OP_UNICODE_BYTE | flags);
+                        self.out.push(c as u8);
+                    }
+                    _ if self.dialect.ucs2_unicode => {
+                        let units: Vec<u16> = s.encode_utf16().collect();
+                        self.out.push(OP_UCS2_STRING | flags);
+                        self.write_size(units.len())?;
+                        for unit in units {
+                            self.out.extend_from_slice(&unit.to_le_bytes());
+                        }
+                    }
+                    _ => {
+                        self.out.push(OP_UTF8_STRING | flags);
+                        self.write_sized(s.as_bytes())?;
+                    }
+                }
+            }
+            Value::Buffer(bytes) => {
+                self.out.push(OP_BUFFER | flags);
+                self.write_sized(bytes)?;
+            }
+            Value::Token(name) => {
+                self.out.push(OP_TOKEN | flags);
+                self.write_sized(&latin1_bytes(name)?)?;
+            }
+            Value::Interned(index) => {
+                self.out.push(OP_STRING_TABLE | flags);
+                self.out.push(*index);
+            }
+            Value::Tuple(items) => {
+                match items.len() {
+                    0 => self.out.push(OP_EMPTY_TUPLE | flags),
+                    1 => self.out.push(OP_ONE_TUPLE | flags),
+                    2 => self.out.push(OP_TWO_TUPLE | flags),
+                    len => {
+                        self.out.push(OP_TUPLE | flags);
+                        self.write_size(len)?;
+                    }
+                }
+                self.write_values(items)?;
+            }
+            Value::List(items) => {
+                match items.len() {
+                    0 => self.out.push(OP_EMPTY_LIST | flags),
+                    1 => self.out.push(OP_ONE_LIST | flags),
+                    len => {
+                        self.out.push(OP_LIST | flags);
+                        self.write_size(len)?;
+                    }
+                }
+                self.write_values(items)?;
+            }
+            Value::Dict(entries) => {
+                self.out.push(OP_DICT | flags);
+                self.write_size(entries.len())?;
+                for (key, value) in entries {
+                    self.write_value(value, 0)?;
+                    self.write_value(key, 0)?;
+                }
+            }
+            Value::Object { class, args } => {
+                self.out.push(OP_OBJECT | flags);
+                self.write_value(class, 0)?;
+                self.write_value(args, 0)?;
+            }
+            Value::ObjectEx {
+                reduce,
+                header,
+                list,
+                dict,
+            } => {
+                let opcode = if *reduce {
+                    OP_OBJECT_EX2
+                } else {
+                    OP_OBJECT_EX1
+                };
+                self.out.push(opcode | flags);
+                self.write_value(header, 0)?;
+                self.write_values(list)?;
+                self.out.push(OP_TERMINATOR);
+                for (key, value) in dict {
+                    self.write_value(key, 0)?;
+                    self.write_value(value, 0)?;
+                }
+                self.out.push(OP_TERMINATOR);
+            }
+            Value::SubStruct(inner) => {
+                self.out.push(OP_SUB_STRUCT | flags);
+                self.write_value(inner, 0)?;
+            }
+            Value::SubStream(bytes) => {
+                self.out.push(OP_SUB_STREAM | flags);
+                self.write_sized(bytes)?;
+            }
+            Value::Checksummed { checksum, value } => {
+                self.out.push(OP_CHECKSUMMED | flags);
+                self.out.extend_from_slice(&checksum.to_le_bytes());
+                self.write_value(value, 0)?;
+            }
+            Value::Pickle(bytes) => {
+                self.out.push(OP_PICKLE | flags);
+                self.write_sized(bytes)?;
+            }
+            Value::Shared(..) | Value::Ref(..) | Value::Sized(..) => unreachable!(),
+        }
+        Ok(())
+    }
+}
+
+fn latin1_bytes(s: &str) -> Result<Vec<u8>, String> {
+    s.chars()
+        .map(|c| u8::try_from(c as u32).ok())
+        .collect::<Option<Vec<u8>>>()
+        .ok_or_else(|| format!("String {:?} cannot be stored as a byte string", s))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn stream(body: &[u8], slots: &[u32]) -> Vec<u8> {
+        let mut data = vec![STREAM_HEADER];
+        data.extend_from_slice(&(slots.len() as u32).to_le_bytes());
+        data.extend_from_slice(body);
+        for slot in slots {
+            data.extend_from_slice(&slot.to_le_bytes());
+        }
+        data
+    }
+
+    fn short_string(s: &str, flags: u8) -> Vec<u8> {
+        let mut bytes = vec![OP_SHORT_STRING | flags, s.len() as u8];
+        bytes.extend_from_slice(s.as_bytes());
+        bytes
+    }
+
+    fn assert_round_trip(data: &[u8]) -> Document {
+        let doc = decode(data).unwrap();
+        assert_eq!(encode(&doc).unwrap(), data);
+        doc
+    }
+
+    #[test]
+    fn saved_values_and_refs_round_trip() {
+        // ["abc" saved in slot 1, ref 1]
+        let body = [
+            &[OP_LIST, 2][..],
+            &short_string("abc", FLAG_SAVED),
+            &[OP_SAVED_ELEMENT, 1],
+        ]
+        .concat();
+        let doc = assert_round_trip(&stream(&body, &[1]));
+        let Value::List(items) = doc.root else {
+            panic!("expected a list");
+        };
+        assert!(matches!(items[0], Value::Shared(1, _)));
+        assert!(matches!(items[1], Value::Ref(1, _)));
+        assert_eq!(items[1].as_str(), Some("abc"));
+    }
+
+    #[test]
+    fn out_of_order_slot_map_round_trips() {
+        // The client numbers saved values in the order it met them, not stream order
+        let body = [
+            &[OP_LIST, 4][..],
+            &short_string("xx", FLAG_SAVED),
+            &short_string("yy", FLAG_SAVED),
+            &[OP_SAVED_ELEMENT, 2, OP_SAVED_ELEMENT, 1],
+        ]
+        .concat();
+        let doc = assert_round_trip(&stream(&body, &[2, 1]));
+        let Value::List(items) = doc.root else {
+            panic!("expected a list");
+        };
+        assert_eq!(items[2].as_str(), Some("xx"));
+        assert_eq!(items[3].as_str(), Some("yy"));
+    }
+
+    #[test]
+    fn sparse_slots_are_renumbered() {
+        let body = [
+            &[OP_LIST, 3][..],
+            &short_string("xx", FLAG_SAVED),
+            &short_string("yy", FLAG_SAVED),
+            &[OP_SAVED_ELEMENT, 2],
+        ]
+        .concat();
+        let mut doc = decode(&stream(&body, &[1, 2])).unwrap();
+        if let Value::List(items) = &mut doc.root {
+            items.remove(0);
+        }
+
+        // Only slot 2 is left, so it becomes slot 1 and the ref follows it
+        let expected = [
+            &[OP_LIST, 2][..],
+            &short_string("yy", FLAG_SAVED),
+            &[OP_SAVED_ELEMENT, 1],
+        ]
+        .concat();
+        assert_eq!(encode(&doc).unwrap(), stream(&expected, &[1]));
+    }
+
+    #[test]
+    fn ref_to_dropped_slot_is_inlined() {
+        let body = [
+            &[OP_LIST, 2][..],
+            &short_string("xx", FLAG_SAVED),
+            &[OP_SAVED_ELEMENT, 1],
+        ]
+        .concat();
+        let mut doc = decode(&stream(&body, &[1])).unwrap();
+        if let Value::List(items) = &mut doc.root {
+            items.remove(0);
+        }
+
+        let expected = [&[OP_ONE_LIST][..], &short_string("xx", 0)].concat();
+        assert_eq!(encode(&doc).unwrap(), stream(&expected, &[]));
+    }
+
+    #[test]
+    fn every_int_width_round_trips() {
+        let mut body = vec![OP_LIST, 7, OP_MINUS_ONE, OP_ZERO, OP_ONE];
+        body.extend([OP_BYTE, (-100i8) as u8]);
+        body.push(OP_SIGNED_SHORT);
+        body.extend(1000i16.to_le_bytes());
+        body.push(OP_LONG);
+        body.extend((-70_000i32).to_le_bytes());
+        body.push(OP_LONG_LONG);
+        body.extend(5_000_000_000i64.to_le_bytes());
+
+        let doc = assert_round_trip(&stream(&body, &[]));
+        let Value::List(items) = doc.root else {
+            panic!("expected a list");
+        };
+        let ints: Vec<_> = items.iter().map(Value::as_int).collect();
+        let expected = [-1, 0, 1, -100, 1000, -70_000, 5_000_000_000];
+        assert_eq!(ints, expected.map(Some));
+        assert!(items.iter().all(|item| matches!(item, Value::Int(_))));
+    }
+
+    #[test]
+    fn non_canonical_ints_keep_their_width() {
+        let mut body = vec![OP_TUPLE, 4, OP_BYTE, 1];
+        body.push(OP_SIGNED_SHORT);
+        body.extend((-2i16).to_le_bytes());
+        body.push(OP_LONG | FLAG_SAVED);
+        body.extend(120i32.to_le_bytes());
+        body.push(OP_LONG_LONG);
+        body.extend(0i64.to_le_bytes());
+
+        let doc = assert_round_trip(&stream(&body, &[1]));
+        let Value::Tuple(items) = &doc.root else {
+            panic!("expected a tuple");
+        };
+        assert!(matches!(items[0], Value::Sized(1, _)));
+        let ints: Vec<_> = items.iter().map(Value::as_int).collect();
+        assert_eq!(ints, [Some(1), Some(-2), Some(120), Some(0)]);
+    }
+
+    #[test]
+    fn widths_do_not_affect_comparison() {
+        let mut wide = vec![OP_LIST, 2, OP_LONG];
+        wide.extend(5i32.to_le_bytes());
+        wide.extend([OP_BYTE, 7]);
+        let narrow = [OP_LIST, 2, OP_BYTE, 5, OP_BYTE, 7];
+
+        let wide = decode(&stream(&wide, &[])).unwrap();
+        let narrow = decode(&stream(&narrow, &[])).unwrap();
+        assert_ne!(wide.root, narrow.root);
+        assert_eq!(wide.root.detached(), narrow.root.detached());
+        assert_eq!(wide.root.without_widths(), narrow.root);
+    }
+
+    #[test]
+    fn restored_widths_round_trip_an_edited_tree() {
+        let mut body = vec![OP_DICT, 2, OP_LONG];
+        body.extend(5i32.to_le_bytes());
+        body.extend(short_string("ab", 0));
+        body.extend([OP_SIGNED_SHORT]);
+        body.extend(9i16.to_le_bytes());
+        body.extend(short_string("cd", 0));
+        let data = stream(&body, &[]);
+        let doc = assert_round_trip(&data);
+
+        // What the frontend gets back, with the entries reordered
+        let Value::Dict(mut entries) = doc.root.without_widths() else {
+            panic!("expected a dict");
+        };
+        entries.reverse();
+        let mut edited = Document {
+            root: Value::Dict(entries.clone()),
+            dialect: doc.dialect,
+        };
+        edited.root.restore_widths(&doc.root);
+        entries.reverse();
+        let mut unchanged = Document {
+            root: Value::Dict(entries),
+            dialect: doc.dialect,
+        };
+        unchanged.root.restore_widths(&doc.root);
+        assert_eq!(encode(&unchanged).unwrap(), data);
+
+        let Value::Dict(entries) = &mut edited.root else {
+            panic!("expected a dict");
+        };
+        entries[0].1 = Value::Int(300);
+        let mut expected = vec![OP_DICT, 2, OP_SIGNED_SHORT];
+        expected.extend(300i16.to_le_bytes());
+        expected.extend(short_string("cd", 0));
+        expected.push(OP_LONG);
+        expected.extend(5i32.to_le_bytes());
+        expected.extend(short_string("ab", 0));
+        assert_eq!(encode(&edited).unwrap(), stream(&expected, &[]));
+    }
+
+    #[test]
+    fn edited_sized_int_that_no_longer_fits_is_widened() {
+        let mut body = vec![OP_SIGNED_SHORT];
+        body.extend(5i16.to_le_bytes());
+        let mut doc = decode(&stream(&body, &[])).unwrap();
+        *doc.root.resolve_mut() = Value::Int(100_000);
+
+        let mut expected = vec![OP_LONG];
+        expected.extend(100_000i32.to_le_bytes());
+        assert_eq!(encode(&doc).unwrap(), stream(&expected, &[]));
+    }
+
+    #[test]
+    fn deep_nesting_is_rejected() {
+        let mut body = vec![OP_ONE_LIST; MAX_DEPTH];
+        body.push(OP_NONE);
+        let error = decode(&stream(&body, &[])).unwrap_err();
+        assert!(error.starts_with("Nesting too deep"), "{}", error);
+
+        body.drain(..2);
+        assert_round_trip(&stream(&body, &[]));
+    }
+
+    #[test]
+    fn deep_trees_are_not_encoded() {
+        let mut root = Value::None;
+        for _ in 0..MAX_DEPTH {
+            root = Value::List(vec![root]);
+        }
+        let doc = Document {
+            root,
+            dialect: Dialect::default(),
+        };
+        assert_eq!(encode(&doc).unwrap_err(), "Nesting too deep to encode");
+    }
+
+    #[test]
+    fn settings_file_round_trips() {
+        let data = include_bytes!("../tests/fixtures/core_char_90000001.dat");
+        let doc = assert_round_trip(data);
+        assert!(doc.dialect.short_strings);
+
+        let groups = crate::sections::groups(&doc.root).unwrap();
+        let names: Vec<_> = groups.iter().filter_map(|(k, _)| k.as_str()).collect();
+        assert_eq!(names, ["windows", "cmd", "overview", "audio"]);
+    }
+}
@@ -0,0 +1,203 @@
+use crate::error::{AppError, AppResult, IoResultExt};
+use crate::evesettings::{
+    app_data_dir, apply_aliases, backup_metadata, emit_data_changed, fill_original_names,
+    load_aliases, scan_backups, scan_installations, BackupEntry, Server, SettingsKind,
+};
+use crate::operations::OperationRecorder;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Everything known about a backup, stored as `<backup>.json` next to the `.bak` file.
+/// Backups made before sidecars existed have none and are described by their filename.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct BackupMetadata {
+    pub name: String,
+    pub kind: SettingsKind,
+    pub original_id: String,
+    pub timestamp: u64,
+    #[serde(default)]
+    pub notes: Option<String>,
+    #[serde(default)]
+    pub tags: Vec<String>,
+    #[serde(default)]
+    pub source_path: Option<String>,
+    #[serde(default)]
+    pub server: Option<Server>,
+    #[serde(default)]
+    pub profile: Option<String>,
+    #[serde(default)]
+    pub character_name: Option<String>,
+    #[serde(default)]
+    pub sha256: Option<String>,
+    #[serde(default)]
+    pub app_version: Option<String>,
+}
+
+pub(crate) fn sidecar_path(backup: &Path) -> PathBuf {
+    backup.with_extension("json")
+}
+
+pub(crate) fn read_metadata(backup: &Path) -> Option<BackupMetadata> {
+    let content = fs::read_to_string(sidecar_path(backup)).ok()?;
+    serde_json::from_str(&content).ok()
+}
+
+pub(crate) fn write_metadata(backup: &Path, metadata: &BackupMetadata) -> AppResult<()> {
+    let path = sidecar_path(backup);
+    let content =
+        serde_json::to_string_pretty(metadata).map_err(|e| AppError::other(e.to_string()))?;
+    fs::write(&path, content).at("write", &path)
+}
+
+/// Backup name as it may appear in a filename. The real name lives in the sidecar, so
+/// anything outside a conservative character set is replaced.
+pub(crate) fn filename_safe(name: &str) -> String {
+    let safe: String = name
+        .trim()
+        .chars()
+        .map(|c| {
+            if c.is_alphanumeric() || c == '-' || c == '_' || c == ' ' {
+                c
+            } else {
+                '-'
+            }
+        })
+        .collect();
+
+    if safe.is_empty() {
+        "backup".to_string()
+    } else {
+        safe
+    }
+}
+
+/// Filters for `search_backups`. Every field is optional and all given ones must match.
+#[derive(Deserialize, Debug, Clone, Default)]
+#[serde(default)]
+pub struct BackupQuery {
+    /// Tags the backup must all carry, compared case-insensitively.
+    pub tags: Vec<String>,
+    pub kind: Option<SettingsKind>,
+    pub server: Option<Server>,
+    pub profile: Option<String>,
+    pub original_id: Option<String>,
+    /// Unix timestamps, inclusive.
+    pub from: Option<u64>,
+    pub to: Option<u64>,
+    /// Matched against name, notes, tags, id and the entity's name: its alias, or the
+    /// character name recorded with the backup.
+    pub text: Option<String>,
+}
+
+impl BackupQuery {
+    fn matches(&self, backup: &BackupEntry) -> bool {
+        let has_tag = |tag: &String| backup.tags.iter().any(|t| t.eq_ignore_ascii_case(tag));
+
+        self.tags.iter().all(has_tag)
+            && self.kind.is_none_or(|kind| backup.kind == kind)
+            && self
+                .server
+                .is_none_or(|server| backup.server == Some(server))
+            && self
+                .profile
+                .as_ref()
+                .is_none_or(|profile| backup.profile.as_ref() == Some(profile))
+            && self
+                .original_id
+                .as_ref()
+                .is_none_or(|id| &backup.original_id == id)
+            && self.from.is_none_or(|from| backup.timestamp >= from)
+            && self.to.is_none_or(|to| backup.timestamp <= to)
+            && self
+                .text
+                .as_ref()
+                .is_none_or(|text| text_matches(backup, text))
+    }
+}
+
+fn text_matches(backup: &BackupEntry, text: &str) -> bool {
+    let needle = text.trim().to_lowercase();
+    if needle.is_empty() {
+        return true;
+    }
+
+    [
+        Some(&backup.name),
+        backup.notes.as_ref(),
+        backup.original_name.as_ref(),
+        Some(&backup.original_id),
+    ]
+    .into_iter()
+    .flatten()
+    .chain(backup.tags.iter())
+    .any(|field| field.to_lowercase().contains(&needle))
+}
+
+/// Trimmed, non-empty tags without case-insensitive duplicates, in the order given.
+fn normalize_tags(tags: Vec<String>) -> Vec<String> {
+    let mut result: Vec<String> = Vec::new();
+    for tag in tags {
+        let tag = tag.trim();
+        if !tag.is_empty() && !result.iter().any(|t| t.eq_ignore_ascii_case(tag)) {
+            result.push(tag.to_string());
+        }
+    }
+    result
+}
+
+/// Replaces a backup's notes and tags. Backups without a sidecar get one.
+#[tauri::command]
+pub fn update_backup_metadata(
+    app: tauri::AppHandle,
+    backup_path: String,
+    notes: Option<String>,
+    tags: Vec<String>,
+) -> AppResult<BackupMetadata> {
+    let path = PathBuf::from(&backup_path);
+    if !path.is_file() {
+        return Err(AppError::not_found("Backup file not found").with_path(&path));
+    }
+    let mut metadata = backup_metadata(&path).ok_or_else(|| {
+        AppError::invalid_input(format!("Not a backup file: {}", path.display())).with_path(&path)
+    })?;
+
+    metadata.notes = notes
+        .map(|n| n.trim().to_string())
+        .filter(|n| !n.is_empty());
+    metadata.tags = normalize_tags(tags);
+
+    let mut recorder = OperationRecorder::begin(
+        &app_data_dir(&app)?,
+        "update_backup_metadata",
+        serde_json::json!({
+            "backup_path": backup_path,
+            "notes": metadata.notes,
+            "tags": metadata.tags,
+        }),
+    )?;
+    recorder.snapshot(&sidecar_path(&path))?;
+    write_metadata(&path, &metadata)?;
+
+    emit_data_changed(&app);
+    Ok(metadata)
+}
+
+/// Backups matching `query`, newest first.
+#[tauri::command]
+pub fn search_backups(
+    app: tauri::AppHandle,
+    custom_eve_path: Option<String>,
+    query: BackupQuery,
+) -> AppResult<Vec<BackupEntry>> {
+    let mut backups = scan_backups(custom_eve_path.as_deref())?;
+    if query.text.is_some() {
+        // Names come from aliases and sidecars; ESI lookups are left to get_app_data
+        let (mut server_profiles, _) = scan_installations(custom_eve_path.as_deref())?;
+        apply_aliases(&mut server_profiles, &load_aliases(&app_data_dir(&app)?));
+        fill_original_names(&mut backups, server_profiles.values().flatten());
+    }
+
+    backups.retain(|backup| query.matches(backup));
+    Ok(backups)
+}
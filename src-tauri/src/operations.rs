@@ -0,0 +1,398 @@
+use crate::clients::{self, RunningClient};
+use crate::error::{AppError, AppResult, ErrorCode, IoResultExt};
+use crate::evesettings::{
+    app_data_dir, backup_settings_file, emit_data_changed, sha256_of_file, BackupEntry,
+    TargetResult,
+};
+use crate::store;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+const RECORD_FILE: &str = "operation.json";
+/// Operations kept in the journal; older ones are dropped as new ones are recorded.
+const MAX_OPERATIONS: usize = 200;
+/// Folders without a record younger than this may belong to an operation in progress.
+const UNFINISHED_GRACE: Duration = Duration::from_secs(3600);
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct FileSnapshot {
+    pub path: String,
+    /// Copy of the file taken before the operation touched it; `None` when the file
+    /// did not exist yet.
+    pub snapshot: Option<String>,
+    /// Regular backup taken of the file instead of a journal copy. Its content is
+    /// restored from the backup store by `before_sha256`, which keeps that blob alive
+    /// for as long as this record exists, even if the backup itself is deleted.
+    #[serde(default)]
+    pub backup: Option<String>,
+    pub before_sha256: Option<String>,
+    /// Hash once the operation finished; `None` when it left no file behind.
+    #[serde(default)]
+    pub after_sha256: Option<String>,
+    /// Already put back by an undo that failed for other files, so a retry skips it.
+    #[serde(default)]
+    pub restored: bool,
+}
+
+/// One user action that modified files, with everything needed to put them back.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct Operation {
+    pub id: String,
+    pub kind: String,
+    pub timestamp: u64,
+    /// Arguments the command was called with.
+    #[serde(default)]
+    pub inputs: serde_json::Value,
+    pub files: Vec<FileSnapshot>,
+    /// Set once every file has been put back.
+    #[serde(default)]
+    pub undone_at: Option<u64>,
+}
+
+#[derive(Serialize, Debug, Clone)]
+pub struct UndoResult {
+    pub operation: Operation,
+    /// Outcome for each file the undo tried to put back.
+    pub files: Vec<TargetResult>,
+}
+
+fn operations_dir(data_dir: &Path) -> AppResult<PathBuf> {
+    let path = data_dir.join("operations");
+    fs::create_dir_all(&path).at("create", &path)?;
+    Ok(path)
+}
+
+// Drops the oldest operations beyond `MAX_OPERATIONS`. A folder without a record is
+// counted by its modification time once it is too old to belong to an operation still
+// in progress, so snapshots left by a crash are rotated out like everything else.
+fn prune_journal(root: &Path) {
+    let Ok(entries) = fs::read_dir(root) else {
+        return;
+    };
+    let mut operations: Vec<(u64, String, PathBuf)> = entries
+        .flatten()
+        .filter_map(|entry| {
+            let path = entry.path();
+            if let Ok(operation) = read_record(&path) {
+                return Some((operation.timestamp, operation.id, path));
+            }
+            let modified = entry.metadata().and_then(|m| m.modified()).ok()?;
+            let age = SystemTime::now().duration_since(modified).ok()?;
+            if !path.is_dir() || age < UNFINISHED_GRACE {
+                return None;
+            }
+            let timestamp = modified.duration_since(UNIX_EPOCH).ok()?.as_secs();
+            let id = entry.file_name().to_string_lossy().into_owned();
+            Some((timestamp, id, path))
+        })
+        .collect();
+    if operations.len() < MAX_OPERATIONS {
+        return;
+    }
+
+    operations.sort_unstable_by(|a, b| b.0.cmp(&a.0).then_with(|| b.1.cmp(&a.1)));
+    // One slot is left for the operation about to start
+    for (_, _, dir) in operations.into_iter().skip(MAX_OPERATIONS - 1) {
+        let _ = fs::remove_dir_all(dir);
+    }
+}
+
+fn is_valid_id(id: &str) -> bool {
+    !id.is_empty()
+        && id
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+}
+
+/// Hashes of the backup store blobs that records restore from.
+pub(crate) fn pinned_blobs(data_dir: &Path) -> Vec<String> {
+    let Ok(operations) = fs::read_dir(data_dir.join("operations")) else {
+        return Vec::new();
+    };
+    operations
+        .flatten()
+        .filter_map(|entry| read_record(&entry.path()).ok())
+        .flat_map(|operation| operation.files)
+        .filter(|file| file.backup.is_some())
+        .filter_map(|file| file.before_sha256)
+        .collect()
+}
+
+/// Every snapshot file in the journal.
+pub(crate) fn snapshot_files(data_dir: &Path) -> Vec<PathBuf> {
+    let Ok(operations) = fs::read_dir(data_dir.join("operations")) else {
+        return Vec::new();
+    };
+    operations
+        .flatten()
+        .filter_map(|operation| fs::read_dir(operation.path()).ok())
+        .flatten()
+        .flatten()
+        .map(|entry| entry.path())
+        .filter(|path| path.file_name().is_some_and(|name| name != RECORD_FILE))
+        .collect()
+}
+
+fn hash_if_exists(path: &Path) -> AppResult<Option<String>> {
+    if path.exists() {
+        sha256_of_file(path).map(Some)
+    } else {
+        Ok(None)
+    }
+}
+
+fn write_record(dir: &Path, operation: &Operation) -> AppResult<()> {
+    let content =
+        serde_json::to_string_pretty(operation).map_err(|e| AppError::other(e.to_string()))?;
+    let path = dir.join(RECORD_FILE);
+    fs::write(&path, content).at("write", &path)
+}
+
+fn read_record(dir: &Path) -> AppResult<Operation> {
+    let path = dir.join(RECORD_FILE);
+    let content = fs::read_to_string(&path).at("read", &path)?;
+    serde_json::from_str(&content).map_err(|e| {
+        AppError::invalid_data(format!("Invalid operation record: {}", e)).with_path(&path)
+    })
+}
+
+/// Collects pre-change snapshots for one mutating command. Call `snapshot` right before
+/// each file is overwritten or removed, or `back_up` where the command also leaves a
+/// regular safety backup; the operation record is written when the
+/// recorder is dropped, so early returns still leave a complete record of what was
+/// touched. Operations that snapshot nothing leave no trace.
+///
+/// Snapshotting a settings file also checks it is safe to write, so every mutating
+/// command refuses while the file's client is running.
+pub(crate) struct OperationRecorder {
+    operation: Operation,
+    dir: PathBuf,
+    /// Looked up on the first settings file snapshotted, then reused.
+    clients: Option<Vec<RunningClient>>,
+}
+
+impl OperationRecorder {
+    pub(crate) fn begin(data_dir: &Path, kind: &str, inputs: serde_json::Value) -> AppResult<Self> {
+        let root = operations_dir(data_dir)?;
+        prune_journal(&root);
+        let millis = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_err(|e| AppError::other(e.to_string()))?
+            .as_millis();
+
+        let mut id = millis.to_string();
+        let mut n = 1;
+        while root.join(&id).exists() {
+            id = format!("{}-{}", millis, n);
+            n += 1;
+        }
+
+        let dir = root.join(&id);
+        fs::create_dir_all(&dir).at("create", &dir)?;
+
+        Ok(Self {
+            operation: Operation {
+                id,
+                kind: kind.to_string(),
+                timestamp: (millis / 1000) as u64,
+                inputs,
+                files: Vec::new(),
+                undone_at: None,
+            },
+            dir,
+            clients: None,
+        })
+    }
+
+    /// Saves the current state of `path`. Only the first snapshot of a path counts, so
+    /// the record always holds the state from before the operation started. A backup
+    /// reference is saved with the content it points at, so the snapshot does not
+    /// depend on the blob surviving garbage collection.
+    pub(crate) fn snapshot(&mut self, path: &Path) -> AppResult<()> {
+        let key = path.to_string_lossy().into_owned();
+        if self.is_recorded(&key) {
+            return Ok(());
+        }
+        self.check_writable(path)?;
+
+        let before_sha256 = hash_if_exists(path)?;
+        let snapshot = if before_sha256.is_some() {
+            let filename = path.file_name().and_then(|n| n.to_str()).unwrap_or("file");
+            let dest = self
+                .dir
+                .join(format!("{}_{}", self.operation.files.len(), filename));
+            fs::copy(store::content_path(path)?, &dest).at("snapshot", path)?;
+            Some(dest.to_string_lossy().into_owned())
+        } else {
+            None
+        };
+
+        self.operation.files.push(FileSnapshot {
+            path: key,
+            snapshot,
+            backup: None,
+            before_sha256,
+            after_sha256: None,
+            restored: false,
+        });
+        Ok(())
+    }
+
+    /// Takes a regular `name` backup of the settings file at `path` and records it in
+    /// place of a snapshot, so the file is only copied once. Refuses, without backing
+    /// anything up, while the file's client is running.
+    pub(crate) fn back_up(&mut self, path: &Path, name: &str) -> AppResult<BackupEntry> {
+        let key = path.to_string_lossy().into_owned();
+        self.check_writable(path)?;
+        let entry = backup_settings_file(path, name, None)?;
+        if self.is_recorded(&key) {
+            return Ok(entry);
+        }
+
+        let before_sha256 = match store::recorded_sha256(Path::new(&entry.path)) {
+            Some(sha256) => sha256,
+            None => sha256_of_file(path)?,
+        };
+        self.operation.files.push(FileSnapshot {
+            path: key,
+            snapshot: None,
+            backup: Some(entry.path.clone()),
+            before_sha256: Some(before_sha256),
+            after_sha256: None,
+            restored: false,
+        });
+        Ok(entry)
+    }
+
+    fn is_recorded(&self, key: &str) -> bool {
+        self.operation.files.iter().any(|f| f.path == key)
+    }
+
+    fn check_writable(&mut self, path: &Path) -> AppResult<()> {
+        if clients::written_by_client(path) {
+            let clients = self.clients.get_or_insert_with(clients::running_clients);
+            clients::check_writable(path, clients)?;
+        }
+        Ok(())
+    }
+
+    fn save(&mut self) -> AppResult<()> {
+        if self.operation.files.is_empty() {
+            return fs::remove_dir_all(&self.dir).at("delete", &self.dir);
+        }
+        for file in self.operation.files.iter_mut() {
+            file.after_sha256 = hash_if_exists(Path::new(&file.path))?;
+        }
+        write_record(&self.dir, &self.operation)
+    }
+}
+
+impl Drop for OperationRecorder {
+    fn drop(&mut self) {
+        let _ = self.save();
+    }
+}
+
+#[tauri::command]
+pub fn list_operations(app: tauri::AppHandle) -> AppResult<Vec<Operation>> {
+    let root = operations_dir(&app_data_dir(&app)?)?;
+    let mut operations: Vec<Operation> = fs::read_dir(&root)
+        .at("read", &root)?
+        .flatten()
+        .filter_map(|entry| read_record(&entry.path()).ok())
+        .collect();
+
+    operations.sort_by(|a, b| b.timestamp.cmp(&a.timestamp).then(b.id.cmp(&a.id)));
+    Ok(operations)
+}
+
+fn restore_file(file: &FileSnapshot, recorder: &mut OperationRecorder) -> AppResult<()> {
+    let path = Path::new(&file.path);
+    recorder.snapshot(path)?;
+
+    match (&file.backup, &file.snapshot, &file.before_sha256) {
+        (Some(backup), _, Some(sha256)) => {
+            let data = store::blob_contents(Path::new(backup), sha256)?;
+            fs::write(path, data).at("restore", path)
+        }
+        (_, Some(snapshot), _) => {
+            fs::copy(snapshot, path).at("restore", path)?;
+            Ok(())
+        }
+        _ if path.exists() => fs::remove_file(path).at("remove", path),
+        _ => Ok(()),
+    }
+}
+
+/// Puts every file touched by an operation back the way it was. Refuses when any of
+/// them changed after the operation, since that later change would be lost. Files are
+/// restored independently: the ones that were put back are recorded, so after a partial
+/// failure a retry only handles the rest. The undo is itself journaled, so it can be
+/// undone in turn.
+#[tauri::command]
+pub fn undo_operation(app: tauri::AppHandle, id: String) -> AppResult<UndoResult> {
+    if !is_valid_id(&id) {
+        return Err(AppError::invalid_input(format!(
+            "Invalid operation id: {}",
+            id
+        )));
+    }
+    let data_dir = app_data_dir(&app)?;
+    let dir = operations_dir(&data_dir)?.join(&id);
+    let mut operation = read_record(&dir)?;
+
+    if operation.undone_at.is_some() {
+        return Err(AppError::new(
+            ErrorCode::Conflict,
+            "Operation has already been undone",
+        ));
+    }
+
+    let changed: Vec<&str> = operation
+        .files
+        .iter()
+        .filter(|f| !f.restored)
+        .filter(|f| hash_if_exists(Path::new(&f.path)).ok().as_ref() != Some(&f.after_sha256))
+        .map(|f| f.path.as_str())
+        .collect();
+    if !changed.is_empty() {
+        return Err(AppError::new(
+            ErrorCode::Conflict,
+            format!("Files changed since this operation: {}", changed.join(", ")),
+        ));
+    }
+
+    let mut files = Vec::new();
+    {
+        let mut recorder = OperationRecorder::begin(
+            &data_dir,
+            "undo_operation",
+            serde_json::json!({ "id": operation.id }),
+        )?;
+
+        for file in operation.files.iter_mut().filter(|f| !f.restored) {
+            let error = restore_file(file, &mut recorder).err();
+            file.restored = error.is_none();
+            files.push(TargetResult {
+                path: file.path.clone(),
+                error,
+            });
+        }
+    }
+
+    if operation.files.iter().all(|f| f.restored) {
+        operation.undone_at = Some(
+            SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map_or(0, |d| d.as_secs()),
+        );
+    }
+    write_record(&dir, &operation)?;
+
+    if files.iter().any(|f| f.error.is_none()) {
+        emit_data_changed(&app);
+    }
+    Ok(UndoResult { operation, files })
+}
@@ -0,0 +1,413 @@
+use crate::clients;
+use crate::error::{AppError, AppResult, IoResultExt};
+use crate::evesettings::{
+    app_data_dir, emit_data_changed, parse_settings_filename, read_editable_settings,
+    read_settings_document, write_settings_document, SettingsKind, TargetResult,
+};
+use crate::marshal::Value;
+use crate::operations::OperationRecorder;
+use crate::sections;
+use serde::Serialize;
+use serde_yaml::{Mapping, Value as Yaml};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+const OVERVIEW_GROUP: &str = "overview";
+const USER_SETTINGS_KEY: &str = "userSettings";
+const PRESETS_KEY: &str = "overviewProfilePresets";
+const TABS_KEY: &str = "tabsettings";
+
+// Top-level keys of the community overview YAML format, the settings key each one is
+// stored under, and how many levels of nested [key, value] pair lists are dicts.
+const OVERVIEW_KEYS: &[(&str, &str, usize)] = &[
+    ("backgroundOrder", "backgroundOrder", 0),
+    ("backgroundStates", "backgroundStates", 0),
+    ("columnOrder", "columnOrder", 0),
+    ("flagOrder", "flagOrder", 0),
+    ("flagStates", "flagStates", 0),
+    ("overviewColumns", "overviewColumns", 0),
+    ("presets", PRESETS_KEY, 2),
+    ("shipLabelOrder", "shipLabelOrder", 0),
+    ("shipLabels", "shipLabels", 0),
+    ("stateBlinks", "stateBlinks", 1),
+    ("stateColors", "stateColors", 1),
+    ("tabSetup", TABS_KEY, 2),
+];
+
+#[derive(Serialize, Debug, Clone)]
+pub struct OverviewExportResult {
+    pub path: String,
+    pub preset_count: usize,
+    pub tab_count: usize,
+}
+
+fn entry<'a>(entries: &'a [(Value, Value)], key: &str) -> Option<&'a Value> {
+    entries
+        .iter()
+        .find(|(k, _)| k.as_str() == Some(key))
+        .map(|(_, v)| v)
+}
+
+fn value_to_yaml(value: &Value) -> Yaml {
+    match value.resolve() {
+        Value::None => Yaml::Null,
+        Value::Bool(b) => Yaml::Bool(*b),
+        Value::Int(i) => Yaml::Number((*i).into()),
+        Value::Float(f) => Yaml::Number((*f).into()),
+        Value::Str(s) | Value::Unicode(s) | Value::Token(s) => Yaml::String(s.clone()),
+        Value::Tuple(items) | Value::List(items) => {
+            Yaml::Sequence(items.iter().map(value_to_yaml).collect())
+        }
+        // The community format writes every dict as a list of [key, value] pairs
+        Value::Dict(entries) => Yaml::Sequence(
+            entries
+                .iter()
+                .map(|(k, v)| Yaml::Sequence(vec![value_to_yaml(k), value_to_yaml(v)]))
+                .collect(),
+        ),
+        // Anything exotic keeps its tagged form so it survives a round trip
+        other => serde_yaml::to_value(other.detached()).unwrap_or(Yaml::Null),
+    }
+}
+
+fn is_pair_list(items: &[Yaml]) -> bool {
+    items
+        .iter()
+        .all(|item| matches!(item, Yaml::Sequence(pair) if pair.len() == 2))
+}
+
+/// Converts YAML back into a settings value. `template` is the value currently stored
+/// under the same key, used to pick tuple vs list, byte vs unicode strings and which
+/// pair lists are dicts; `dict_depth` covers the cases where no template exists.
+fn yaml_to_value(yaml: &Yaml, template: Option<&Value>, dict_depth: usize) -> Value {
+    let template = template.map(Value::resolve);
+
+    match yaml {
+        Yaml::Null => Value::None,
+        Yaml::Bool(b) => Value::Bool(*b),
+        Yaml::Number(n) => match (n.as_i64(), template) {
+            (Some(i), Some(Value::Float(_))) => Value::Float(i as f64),
+            (Some(i), _) => Value::Int(i),
+            (None, _) => Value::Float(n.as_f64().unwrap_or(0.0)),
+        },
+        Yaml::String(s) => match template {
+            Some(Value::Unicode(_)) => Value::Unicode(s.clone()),
+            _ if s.chars().all(|c| (c as u32) <= 0xFF) => Value::Str(s.clone()),
+            _ => Value::Unicode(s.clone()),
+        },
+        Yaml::Sequence(items) => {
+            let as_dict = match template {
+                Some(Value::Dict(_)) => is_pair_list(items),
+                Some(_) => false,
+                None => dict_depth > 0 && is_pair_list(items),
+            };
+
+            if as_dict {
+                let template_entries = template.and_then(Value::as_dict);
+                let entries = items
+                    .iter()
+                    .filter_map(|item| match item {
+                        Yaml::Sequence(pair) => Some((&pair[0], &pair[1])),
+                        _ => None,
+                    })
+                    .map(|(k, v)| {
+                        let template_entry = template_entries.and_then(|entries| {
+                            entries.iter().find(|(tk, _)| value_to_yaml(tk) == *k)
+                        });
+                        let key = yaml_to_value(k, template_entry.map(|(tk, _)| tk), 0);
+                        let value = yaml_to_value(
+                            v,
+                            template_entry.map(|(_, tv)| tv),
+                            dict_depth.saturating_sub(1),
+                        );
+                        (key, value)
+                    })
+                    .collect();
+                return Value::Dict(entries);
+            }
+
+            // Items are matched by position, so mixed lists such as [0.5, 1] keep the
+            // type of each item; extra items follow the first
+            let item_templates = template.and_then(|t| match t {
+                Value::Tuple(items) | Value::List(items) => Some(items),
+                _ => None,
+            });
+            let converted = items
+                .iter()
+                .enumerate()
+                .map(|(i, item)| {
+                    let item_template = item_templates.and_then(|t| t.get(i).or_else(|| t.first()));
+                    yaml_to_value(item, item_template, dict_depth)
+                })
+                .collect();
+
+            match template {
+                Some(Value::Tuple(_)) => Value::Tuple(converted),
+                _ => Value::List(converted),
+            }
+        }
+        Yaml::Mapping(map) => match serde_yaml::from_value::<Value>(yaml.clone()) {
+            Ok(value) => value,
+            Err(_) => Value::Dict(
+                map.iter()
+                    .map(|(k, v)| (yaml_to_value(k, None, 0), yaml_to_value(v, None, 0)))
+                    .collect(),
+            ),
+        },
+        Yaml::Tagged(tagged) => yaml_to_value(&tagged.value, template, dict_depth),
+    }
+}
+
+fn extract_overview(root: &Value) -> AppResult<Mapping> {
+    let group = sections::group_dict(root, OVERVIEW_GROUP)
+        .ok_or_else(|| AppError::invalid_data("No overview settings found in this file"))?;
+
+    let mut mapping = Mapping::new();
+    for (yaml_key, settings_key, _) in OVERVIEW_KEYS {
+        if let Some(value) = entry(group, settings_key) {
+            mapping.insert(Yaml::String(yaml_key.to_string()), value_to_yaml(value));
+        }
+    }
+
+    let user_settings: Vec<Yaml> = group
+        .iter()
+        .filter(|(k, _)| {
+            !OVERVIEW_KEYS
+                .iter()
+                .any(|(_, settings_key, _)| k.as_str() == Some(settings_key))
+        })
+        .map(|(k, v)| Yaml::Sequence(vec![value_to_yaml(k), value_to_yaml(v)]))
+        .collect();
+    if !user_settings.is_empty() {
+        mapping.insert(
+            Yaml::String(USER_SETTINGS_KEY.to_string()),
+            Yaml::Sequence(user_settings),
+        );
+    }
+
+    Ok(mapping)
+}
+
+fn apply_overview(root: &mut Value, overview: &Mapping) -> AppResult<usize> {
+    let mut changed = 0;
+
+    for (yaml_key, settings_key, dict_depth) in OVERVIEW_KEYS {
+        let Some(yaml) = overview.get(*yaml_key) else {
+            continue;
+        };
+        let current = sections::group_dict(root, OVERVIEW_GROUP)
+            .and_then(|group| entry(group, settings_key))
+            .cloned();
+        let value = yaml_to_value(yaml, current.as_ref(), *dict_depth);
+        if sections::set_entry(root, OVERVIEW_GROUP, settings_key, value)? {
+            changed += 1;
+        }
+    }
+
+    if let Some(Yaml::Sequence(pairs)) = overview.get(USER_SETTINGS_KEY) {
+        for pair in pairs {
+            let (Some(key), Some(yaml)) = (pair.get(0).and_then(Yaml::as_str), pair.get(1)) else {
+                continue;
+            };
+            let current = sections::group_dict(root, OVERVIEW_GROUP)
+                .and_then(|group| entry(group, key))
+                .cloned();
+            let value = yaml_to_value(yaml, current.as_ref(), 0);
+            if sections::set_entry(root, OVERVIEW_GROUP, key, value)? {
+                changed += 1;
+            }
+        }
+    }
+
+    Ok(changed)
+}
+
+fn read_overview_yaml(path: &Path) -> AppResult<Mapping> {
+    let content = fs::read_to_string(path).at("read", path)?;
+    let mapping: Mapping = serde_yaml::from_str(&content).map_err(|e| {
+        AppError::invalid_data(format!("Invalid overview YAML: {}", e)).with_path(path)
+    })?;
+
+    let known = OVERVIEW_KEYS
+        .iter()
+        .map(|(yaml_key, _, _)| *yaml_key)
+        .chain([USER_SETTINGS_KEY]);
+    if !known.into_iter().any(|key| mapping.contains_key(key)) {
+        return Err(
+            AppError::invalid_data("File does not contain any overview settings").with_path(path),
+        );
+    }
+    Ok(mapping)
+}
+
+fn import_overview_into(
+    target: &Path,
+    overview: &Mapping,
+    recorder: &mut OperationRecorder,
+) -> AppResult<()> {
+    // The overview is per character; an account file would just grow a stray group
+    let kind = target
+        .file_name()
+        .and_then(|n| n.to_str())
+        .and_then(parse_settings_filename)
+        .map(|(kind, _)| kind);
+    if kind != Some(SettingsKind::Char) {
+        return Err(AppError::invalid_input(
+            "Overview settings can only be imported into character settings files",
+        )
+        .with_path(target));
+    }
+    let mut doc = read_editable_settings(target)?;
+
+    if apply_overview(&mut doc.root, overview).map_err(|e| e.with_path(target))? > 0 {
+        recorder.back_up(target, "pre_overview")?;
+        write_settings_document(target, &doc)?;
+    }
+    Ok(())
+}
+
+#[tauri::command]
+pub fn export_overview(
+    source_path: String,
+    export_path: String,
+) -> AppResult<OverviewExportResult> {
+    let doc = read_settings_document(Path::new(&source_path))?;
+    let overview = extract_overview(&doc.root)?;
+
+    let group = sections::group_dict(&doc.root, OVERVIEW_GROUP);
+    let count = |key: &str| {
+        group
+            .and_then(|g| entry(g, key))
+            .and_then(Value::as_dict)
+            .map_or(0, |d| d.len())
+    };
+
+    let content = serde_yaml::to_string(&overview).map_err(|e| AppError::other(e.to_string()))?;
+    fs::write(&export_path, content).at("write", Path::new(&export_path))?;
+
+    Ok(OverviewExportResult {
+        preset_count: count(PRESETS_KEY),
+        tab_count: count(TABS_KEY),
+        path: export_path,
+    })
+}
+
+#[tauri::command]
+pub fn import_overview(
+    app: tauri::AppHandle,
+    yaml_path: String,
+    target_paths: Vec<String>,
+) -> AppResult<Vec<TargetResult>> {
+    let results = import_overview_files(&app_data_dir(&app)?, yaml_path, target_paths)?;
+    if results.iter().any(|r| r.error.is_none()) {
+        emit_data_changed(&app);
+    }
+    Ok(results)
+}
+
+pub(crate) fn import_overview_files(
+    data_dir: &Path,
+    yaml_path: String,
+    target_paths: Vec<String>,
+) -> AppResult<Vec<TargetResult>> {
+    let overview = read_overview_yaml(Path::new(&yaml_path))?;
+    clients::ensure_writable(&target_paths)?;
+
+    let mut recorder = OperationRecorder::begin(
+        data_dir,
+        "import_overview",
+        serde_json::json!({ "yaml_path": yaml_path, "target_paths": target_paths }),
+    )?;
+    let mut results = Vec::with_capacity(target_paths.len());
+
+    for target_path in target_paths {
+        let error =
+            import_overview_into(&PathBuf::from(&target_path), &overview, &mut recorder).err();
+        results.push(TargetResult {
+            path: target_path,
+            error,
+        });
+    }
+
+    Ok(results)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn text(s: &str) -> Value {
+        Value::Str(s.to_string())
+    }
+
+    fn pairs(entries: Vec<(&str, Value)>) -> Value {
+        Value::Dict(entries.into_iter().map(|(k, v)| (text(k), v)).collect())
+    }
+
+    fn settings(overview: Value) -> Value {
+        Value::Dict(vec![(text(OVERVIEW_GROUP), overview)])
+    }
+
+    fn round_trip(mapping: &Mapping) -> Mapping {
+        serde_yaml::from_str(&serde_yaml::to_string(mapping).unwrap()).unwrap()
+    }
+
+    fn overview_group(root: &Value) -> &Vec<(Value, Value)> {
+        sections::group_dict(root, OVERVIEW_GROUP).unwrap()
+    }
+
+    // Only types that need no template to come back the same way, with the known keys
+    // in the order they are applied
+    fn plain_overview() -> Value {
+        pairs(vec![
+            ("columnOrder", Value::List(vec![text("ICON"), text("NAME")])),
+            (
+                PRESETS_KEY,
+                pairs(vec![(
+                    "PvP",
+                    pairs(vec![("groups", Value::List(vec![Value::Int(25)]))]),
+                )]),
+            ),
+            (
+                "stateColors",
+                pairs(vec![(
+                    "13",
+                    Value::List(vec![Value::Float(0.5), Value::Int(1)]),
+                )]),
+            ),
+            ("overviewBroadcastsToTop", Value::Bool(true)),
+            ("hideCorpTicker", Value::None),
+        ])
+    }
+
+    #[test]
+    fn overview_survives_a_yaml_round_trip() {
+        let source = settings(plain_overview());
+        let yaml = round_trip(&extract_overview(&source).unwrap());
+        assert!(yaml.contains_key(USER_SETTINGS_KEY));
+
+        let mut target = Value::Dict(Vec::new());
+        assert_eq!(apply_overview(&mut target, &yaml).unwrap(), 5);
+        assert_eq!(target, source);
+        assert_eq!(apply_overview(&mut target, &yaml).unwrap(), 0);
+    }
+
+    #[test]
+    fn existing_values_decide_tuples_and_unicode() {
+        let source = settings(pairs(vec![
+            (
+                "columnOrder",
+                Value::Tuple(vec![text("ICON"), text("NAME")]),
+            ),
+            ("tabName", Value::Unicode("Main".to_string())),
+        ]));
+        let yaml = round_trip(&extract_overview(&source).unwrap());
+
+        let mut target = settings(pairs(vec![
+            ("columnOrder", Value::Tuple(vec![text("DISTANCE")])),
+            ("tabName", Value::Unicode("Old".to_string())),
+        ]));
+        assert_eq!(apply_overview(&mut target, &yaml).unwrap(), 2);
+        assert_eq!(overview_group(&target), overview_group(&source));
+    }
+}
@@ -0,0 +1,516 @@
+use crate::error::{AppError, AppResult, IoResultExt};
+use crate::evesettings::{app_data_dir, emit_data_changed};
+use crate::operations::OperationRecorder;
+use serde::Serialize;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+pub(crate) const PREFS_FILE: &str = "prefs.ini";
+
+// ── prefs.ini model ──────────────────────────────────────────────────────
+
+#[derive(Debug, Clone)]
+enum Line {
+    /// Comments, section headers, blank lines and anything unparseable, kept verbatim.
+    Raw(String),
+    Entry {
+        key: String,
+        value: String,
+        raw: String,
+    },
+}
+
+/// A prefs.ini file that round-trips byte for byte: only lines whose value is changed
+/// get rewritten, everything else keeps its original text and position.
+#[derive(Debug, Clone)]
+pub struct PrefsFile {
+    lines: Vec<Line>,
+    crlf: bool,
+    trailing_newline: bool,
+}
+
+impl Default for PrefsFile {
+    fn default() -> Self {
+        Self {
+            lines: Vec::new(),
+            crlf: false,
+            trailing_newline: true,
+        }
+    }
+}
+
+impl PrefsFile {
+    pub fn parse(content: &str) -> Self {
+        let trailing_newline = content.ends_with('\n');
+        let body = content.strip_suffix('\n').unwrap_or(content);
+
+        let lines = if content.is_empty() {
+            Vec::new()
+        } else {
+            body.split('\n')
+                .map(|line| {
+                    let line = line.strip_suffix('\r').unwrap_or(line);
+                    let trimmed = line.trim();
+                    let is_entry = !trimmed.is_empty()
+                        && !trimmed.starts_with([';', '#', '['])
+                        && trimmed.contains('=');
+
+                    match trimmed.split_once('=') {
+                        Some((key, value)) if is_entry => Line::Entry {
+                            key: key.trim().to_string(),
+                            value: value.trim().to_string(),
+                            raw: line.to_string(),
+                        },
+                        _ => Line::Raw(line.to_string()),
+                    }
+                })
+                .collect()
+        };
+
+        Self {
+            lines,
+            crlf: content.contains("\r\n"),
+            trailing_newline,
+        }
+    }
+
+    /// Loads `path`, treating a missing file as empty.
+    pub fn load(path: &Path) -> AppResult<Self> {
+        if !path.exists() {
+            return Ok(Self::default());
+        }
+        let content = fs::read_to_string(path).at("read", path)?;
+        Ok(Self::parse(&content))
+    }
+
+    pub fn save(&self, path: &Path) -> AppResult<()> {
+        fs::write(path, self.render()).at("write", path)
+    }
+
+    pub fn render(&self) -> String {
+        let newline = if self.crlf { "\r\n" } else { "\n" };
+        let mut out = self
+            .lines
+            .iter()
+            .map(|line| match line {
+                Line::Raw(raw) | Line::Entry { raw, .. } => raw.as_str(),
+            })
+            .collect::<Vec<_>>()
+            .join(newline);
+        if self.trailing_newline && !self.lines.is_empty() {
+            out.push_str(newline);
+        }
+        out
+    }
+
+    pub fn entries(&self) -> impl Iterator<Item = (&str, &str)> {
+        self.lines.iter().filter_map(|line| match line {
+            Line::Entry { key, value, .. } => Some((key.as_str(), value.as_str())),
+            Line::Raw(_) => None,
+        })
+    }
+
+    pub fn get(&self, key: &str) -> Option<&str> {
+        self.entries().find(|(k, _)| *k == key).map(|(_, v)| v)
+    }
+
+    /// Sets every occurrence of `key`, appending it if absent. Returns whether the
+    /// file changed.
+    pub fn set(&mut self, key: &str, new_value: &str) -> bool {
+        let mut found = false;
+        let mut changed = false;
+
+        for line in self.lines.iter_mut() {
+            if let Line::Entry { key: k, value, raw } = line {
+                if k == key {
+                    found = true;
+                    if value != new_value {
+                        *value = new_value.to_string();
+                        *raw = format!("{}={}", key, new_value);
+                        changed = true;
+                    }
+                }
+            }
+        }
+
+        if !found {
+            self.lines.push(Line::Entry {
+                key: key.to_string(),
+                value: new_value.to_string(),
+                raw: format!("{}={}", key, new_value),
+            });
+            changed = true;
+        }
+        changed
+    }
+
+    /// Removes every occurrence of `key` so the client falls back to its default.
+    pub fn remove(&mut self, key: &str) -> bool {
+        let before = self.lines.len();
+        self.lines
+            .retain(|line| !matches!(line, Line::Entry { key: k, .. } if k == key));
+        self.lines.len() != before
+    }
+}
+
+// ── Catalogue ────────────────────────────────────────────────────────────
+
+#[derive(Serialize, Debug, Clone, Copy, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum PrefType {
+    Bool,
+    Int,
+    String,
+}
+
+impl PrefType {
+    fn accepts(&self, value: &str) -> bool {
+        match self {
+            PrefType::Bool => value == "0" || value == "1",
+            PrefType::Int => value.parse::<i64>().is_ok(),
+            PrefType::String => !value.contains(['\r', '\n']),
+        }
+    }
+}
+
+#[derive(Serialize, Debug, Clone, Copy, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum PrefRisk {
+    /// Cosmetic or easily reverted.
+    Safe,
+    /// Can hurt performance or stability on some machines.
+    Caution,
+}
+
+#[derive(Serialize, Debug)]
+pub struct PrefDef {
+    pub name: &'static str,
+    pub kind: PrefType,
+    pub default: &'static str,
+    pub description: &'static str,
+    pub risk: PrefRisk,
+}
+
+pub const BRACKETS_ALWAYS_SHOW: &str = "bracketsAlwaysShowShipText";
+
+pub const PREFS: &[PrefDef] = &[
+    PrefDef {
+        name: BRACKETS_ALWAYS_SHOW,
+        kind: PrefType::Bool,
+        default: "0",
+        description: "Show ship labels on all brackets in space, not just selected targets",
+        risk: PrefRisk::Caution,
+    },
+    PrefDef {
+        name: "loadstationenv2",
+        kind: PrefType::Bool,
+        default: "1",
+        description: "Load the 3D station and structure interior when docked",
+        risk: PrefRisk::Safe,
+    },
+    PrefDef {
+        name: "fixedWindow",
+        kind: PrefType::Bool,
+        default: "0",
+        description: "Lock the client window so it cannot be moved or resized",
+        risk: PrefRisk::Safe,
+    },
+    PrefDef {
+        name: "textureQuality",
+        kind: PrefType::Int,
+        default: "0",
+        description: "Texture detail: 0 high, 1 medium, 2 low",
+        risk: PrefRisk::Safe,
+    },
+    PrefDef {
+        name: "breakpadUpload",
+        kind: PrefType::Bool,
+        default: "1",
+        description: "Upload crash reports to CCP when the client crashes",
+        risk: PrefRisk::Safe,
+    },
+    PrefDef {
+        name: "resourceCacheEnabled",
+        kind: PrefType::Bool,
+        default: "1",
+        description: "Keep loaded graphics resources cached in memory",
+        risk: PrefRisk::Caution,
+    },
+    PrefDef {
+        name: "languageID",
+        kind: PrefType::String,
+        default: "EN",
+        description: "Client language code (EN, DE, FR, JA, RU, ZH, KO, ES)",
+        risk: PrefRisk::Safe,
+    },
+];
+
+pub fn find_pref(name: &str) -> Option<&'static PrefDef> {
+    PREFS.iter().find(|p| p.name == name)
+}
+
+// ── Profile access ───────────────────────────────────────────────────────
+
+#[derive(Serialize, Debug, Clone)]
+pub struct ProfilePref {
+    pub profile: String,
+    pub profile_path: String,
+    /// Value written in this profile's prefs.ini; `None` means the client default.
+    pub value: Option<String>,
+}
+
+#[derive(Serialize, Debug, Clone)]
+pub struct PrefSetting {
+    pub definition: &'static PrefDef,
+    /// Value shared by every profile, `None` when unset everywhere or mixed.
+    pub value: Option<String>,
+    /// Profiles disagree on the effective value (defaults taken into account).
+    pub mixed: bool,
+    pub profiles: Vec<ProfilePref>,
+}
+
+#[derive(Serialize, Debug, Clone)]
+pub struct PrefEntry {
+    pub key: String,
+    pub value: String,
+}
+
+#[derive(Serialize, Debug, Clone)]
+pub struct ServerPrefs {
+    pub server_path: String,
+    pub prefs: Vec<PrefSetting>,
+    /// Keys present in some prefs.ini that are not in the catalogue.
+    pub other: Vec<PrefEntry>,
+}
+
+/// Profile folders (settings_*) under a server directory, sorted by name.
+fn profile_dirs(server_path: &Path) -> Vec<PathBuf> {
+    let mut dirs: Vec<PathBuf> = fs::read_dir(server_path)
+        .map(|entries| {
+            entries
+                .flatten()
+                .map(|entry| entry.path())
+                .filter(|path| {
+                    path.is_dir()
+                        && path
+                            .file_name()
+                            .and_then(|n| n.to_str())
+                            .is_some_and(|n| n.starts_with("settings_"))
+                })
+                .collect()
+        })
+        .unwrap_or_default();
+    dirs.sort();
+    dirs
+}
+
+fn profile_name(profile_dir: &Path) -> String {
+    profile_dir
+        .file_name()
+        .and_then(|n| n.to_str())
+        .and_then(|n| n.strip_prefix("settings_"))
+        .unwrap_or_default()
+        .to_string()
+}
+
+/// Whether a boolean preference is on for one profile, falling back to the catalogue
+/// default when the profile does not set it.
+pub(crate) fn profile_flag(profile_dir: &Path, name: &str) -> bool {
+    let value = PrefsFile::load(&profile_dir.join(PREFS_FILE))
+        .ok()
+        .and_then(|prefs| prefs.get(name).map(str::to_string));
+    let default = find_pref(name).map_or("0", |p| p.default);
+    value.as_deref().unwrap_or(default) == "1"
+}
+
+#[tauri::command]
+pub fn get_prefs(server_path: String) -> AppResult<ServerPrefs> {
+    let profiles: Vec<(PathBuf, PrefsFile)> = profile_dirs(Path::new(&server_path))
+        .into_iter()
+        .map(|dir| PrefsFile::load(&dir.join(PREFS_FILE)).map(|prefs| (dir, prefs)))
+        .collect::<Result<_, _>>()?;
+
+    let prefs = PREFS
+        .iter()
+        .map(|definition| {
+            let profiles: Vec<ProfilePref> = profiles
+                .iter()
+                .map(|(dir, prefs)| ProfilePref {
+                    profile: profile_name(dir),
+                    profile_path: dir.to_string_lossy().into_owned(),
+                    value: prefs.get(definition.name).map(str::to_string),
+                })
+                .collect();
+
+            let effective: Vec<&str> = profiles
+                .iter()
+                .map(|p| p.value.as_deref().unwrap_or(definition.default))
+                .collect();
+            let mixed = effective.iter().any(|v| *v != effective[0]);
+            let value = match profiles.first() {
+                Some(first) if profiles.iter().all(|p| p.value == first.value) => {
+                    first.value.clone()
+                }
+                _ => None,
+            };
+
+            PrefSetting {
+                definition,
+                value,
+                mixed,
+                profiles,
+            }
+        })
+        .collect();
+
+    let mut other: Vec<PrefEntry> = Vec::new();
+    for (key, value) in profiles.iter().flat_map(|(_, prefs)| prefs.entries()) {
+        if find_pref(key).is_none() && !other.iter().any(|e| e.key == key) {
+            other.push(PrefEntry {
+                key: key.to_string(),
+                value: value.to_string(),
+            });
+        }
+    }
+
+    Ok(ServerPrefs {
+        server_path,
+        prefs,
+        other,
+    })
+}
+
+/// Writes a catalogued preference to one profile of a server, or to every profile when
+/// `profile_path` is `None`. A `None` value removes the key so the client default
+/// applies again.
+#[tauri::command]
+pub fn set_pref(
+    app: tauri::AppHandle,
+    server_path: String,
+    profile_path: Option<String>,
+    name: String,
+    value: Option<String>,
+) -> AppResult<()> {
+    set_pref_value(&app_data_dir(&app)?, server_path, profile_path, name, value)?;
+    emit_data_changed(&app);
+    Ok(())
+}
+
+pub(crate) fn set_pref_value(
+    data_dir: &Path,
+    server_path: String,
+    profile_path: Option<String>,
+    name: String,
+    value: Option<String>,
+) -> AppResult<()> {
+    let definition = find_pref(&name)
+        .ok_or_else(|| AppError::invalid_input(format!("Unknown preference: {}", name)))?;
+    let value = value.map(|v| v.trim().to_string());
+    if let Some(value) = &value {
+        if !definition.kind.accepts(value) {
+            return Err(AppError::invalid_input(format!(
+                "Invalid value for {}: {}",
+                name, value
+            )));
+        }
+    }
+
+    let mut dirs = profile_dirs(Path::new(&server_path));
+    if let Some(profile_path) = &profile_path {
+        dirs.retain(|dir| dir == Path::new(profile_path));
+        if dirs.is_empty() {
+            return Err(AppError::not_found(format!(
+                "Profile not found in this server: {}",
+                profile_path
+            )));
+        }
+    }
+
+    let mut recorder = OperationRecorder::begin(
+        data_dir,
+        "set_pref",
+        serde_json::json!({
+            "server_path": server_path,
+            "profile_path": profile_path,
+            "name": name,
+            "value": value,
+        }),
+    )?;
+
+    for dir in dirs {
+        let path = dir.join(PREFS_FILE);
+        let mut prefs = PrefsFile::load(&path)?;
+        let changed = match &value {
+            Some(value) => prefs.set(&name, value),
+            None => prefs.remove(&name),
+        };
+        if changed {
+            recorder.snapshot(&path)?;
+            prefs.save(&path)?;
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SAMPLE: &str = "; client preferences\r\n\
+                          [default]\r\n\
+                          windowMode=1\r\n\
+                          \r\n\
+                          # graphics\r\n\
+                          shadowQuality = 2\r\n\
+                          someFutureKey=abc=def\r\n\
+                          not an entry\r\n\
+                          windowMode=1\r\n";
+
+    #[test]
+    fn unchanged_file_renders_byte_for_byte() {
+        for content in [SAMPLE, "", "a=1", "a=1\n\n; trailing comment\n", "\n"] {
+            assert_eq!(PrefsFile::parse(content).render(), content);
+        }
+    }
+
+    #[test]
+    fn unknown_keys_comments_and_order_survive_an_edit() {
+        let mut prefs = PrefsFile::parse(SAMPLE);
+        assert_eq!(prefs.get("someFutureKey"), Some("abc=def"));
+        assert_eq!(prefs.get("shadowQuality"), Some("2"));
+
+        assert!(prefs.set("shadowQuality", "0"));
+        assert_eq!(
+            prefs.render(),
+            SAMPLE.replace("shadowQuality = 2", "shadowQuality=0")
+        );
+        assert!(!prefs.set("shadowQuality", "0"));
+    }
+
+    #[test]
+    fn set_updates_every_occurrence_and_appends_new_keys() {
+        let mut prefs = PrefsFile::parse(SAMPLE);
+        assert!(prefs.set("windowMode", "0"));
+        assert!(prefs.set("newKey", "5"));
+
+        let rendered = prefs.render();
+        assert_eq!(rendered.matches("windowMode=0").count(), 2);
+        assert!(rendered.ends_with("windowMode=0\r\nnewKey=5\r\n"));
+        assert_eq!(PrefsFile::parse(&rendered).render(), rendered);
+    }
+
+    #[test]
+    fn remove_drops_every_occurrence_only() {
+        let mut prefs = PrefsFile::parse(SAMPLE);
+        assert!(prefs.remove("windowMode"));
+        assert!(!prefs.remove("windowMode"));
+        assert_eq!(prefs.get("windowMode"), None);
+        assert_eq!(
+            prefs.render(),
+            SAMPLE
+                .replace("windowMode=1\r\n\r\n", "\r\n")
+                .replace("windowMode=1\r\n", "")
+        );
+    }
+}
@@ -0,0 +1,204 @@
+use crate::error::{AppError, AppResult, IoResultExt};
+use crate::evesettings::{
+    app_data_dir, backup_metadata, emit_data_changed, parse_settings_filename, sha256_of_bytes,
+    BackupEntry, SettingsKind,
+};
+use crate::location;
+use crate::marshal;
+use crate::operations::OperationRecorder;
+use crate::store;
+use filetime::FileTime;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// What the restored file's modification time is set to.
+#[derive(Deserialize, Debug, Clone, Copy, Default, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum RestoreMtime {
+    /// The time of the restore, so the client treats the file as freshly written.
+    #[default]
+    Now,
+    /// The time the backup was taken.
+    Backup,
+    /// Whatever the file being replaced had.
+    Keep,
+}
+
+#[derive(Serialize, Debug, Clone)]
+pub struct RestoreResult {
+    pub target_path: String,
+    pub kind: SettingsKind,
+    pub target_id: String,
+    pub sha256: String,
+    /// Whether the backup carried a recorded hash that the contents were checked against.
+    pub verified: bool,
+    /// Backup of the file that was overwritten, if there was one.
+    pub safety_backup: Option<BackupEntry>,
+}
+
+/// Settings file a backup restores onto when no explicit target is given: the original
+/// file in the backup's profile folder, or the one for `target_id`.
+fn default_target(
+    backup: &Path,
+    source_path: Option<&str>,
+    kind: SettingsKind,
+    id: &str,
+) -> AppResult<PathBuf> {
+    let profile_dir = location::profile_dir_for_backup(backup, source_path).ok_or_else(|| {
+        AppError::not_found(format!("Unknown profile folder for {}", backup.display()))
+            .with_path(backup)
+    })?;
+    Ok(location::settings_path(&profile_dir, kind, id))
+}
+
+/// Reads a backup and checks it is intact: the contents match the recorded hash and
+/// decode as a settings file. Returns the contents and whether a hash was checked.
+fn validated_contents(backup: &Path, recorded: Option<String>) -> AppResult<(Vec<u8>, bool)> {
+    let data = store::read_contents(backup)?;
+    let actual = sha256_of_bytes(&data);
+
+    let verified = match recorded {
+        Some(expected) if expected != actual => {
+            return Err(AppError::invalid_data(format!(
+                "Backup {} is corrupt: expected sha256 {}, found {}",
+                backup.display(),
+                expected,
+                actual
+            ))
+            .with_path(backup));
+        }
+        Some(_) => true,
+        None => false,
+    };
+
+    marshal::decode(&data).map_err(|e| {
+        AppError::invalid_data(format!(
+            "Backup {} is not a valid settings file: {}",
+            backup.display(),
+            e
+        ))
+        .with_path(backup)
+    })?;
+    Ok((data, verified))
+}
+
+/// Restores a backup onto its original settings file, or onto `target_path` / the file
+/// for `target_id` in the same profile. The backup is verified before anything is
+/// written, and the file being replaced is backed up and journaled first.
+#[tauri::command]
+pub fn restore_backup(
+    app: tauri::AppHandle,
+    backup_path: String,
+    target_path: Option<String>,
+    target_id: Option<String>,
+    mtime: Option<RestoreMtime>,
+) -> AppResult<RestoreResult> {
+    let result = restore_backup_file(
+        &app_data_dir(&app)?,
+        backup_path,
+        target_path,
+        target_id,
+        mtime,
+    )?;
+    emit_data_changed(&app);
+    Ok(result)
+}
+
+pub(crate) fn restore_backup_file(
+    data_dir: &Path,
+    backup_path: String,
+    target_path: Option<String>,
+    target_id: Option<String>,
+    mtime: Option<RestoreMtime>,
+) -> AppResult<RestoreResult> {
+    let backup = PathBuf::from(&backup_path);
+    if !backup.is_file() {
+        return Err(AppError::not_found("Backup file not found").with_path(&backup));
+    }
+    let metadata = backup_metadata(&backup).ok_or_else(|| {
+        AppError::invalid_input(format!("Not a backup file: {}", backup.display()))
+            .with_path(&backup)
+    })?;
+
+    let source = metadata.source_path.as_deref();
+    let target = match (&target_path, &target_id) {
+        (Some(path), _) => PathBuf::from(path),
+        (None, Some(id)) => default_target(&backup, source, metadata.kind, id)?,
+        (None, None) => default_target(&backup, source, metadata.kind, &metadata.original_id)?,
+    };
+
+    let filename = target.file_name().and_then(|n| n.to_str()).unwrap_or("");
+    let (kind, id) = parse_settings_filename(filename).ok_or_else(|| {
+        AppError::invalid_input(format!("Not a settings file: {}", target.display()))
+            .with_path(&target)
+    })?;
+    if kind != metadata.kind {
+        return Err(AppError::invalid_input(format!(
+            "Cannot restore a {} backup onto {} settings",
+            metadata.kind.file_tag(),
+            kind.file_tag()
+        )));
+    }
+    let id = id.to_string();
+
+    if !target.parent().is_some_and(Path::is_dir) {
+        return Err(AppError::not_found(format!(
+            "Profile folder not found for {}",
+            target.display()
+        ))
+        .with_path(&target));
+    }
+
+    let recorded = metadata
+        .sha256
+        .clone()
+        .or_else(|| store::recorded_sha256(&backup));
+    let (data, verified) = validated_contents(&backup, recorded)?;
+
+    let mut recorder = OperationRecorder::begin(
+        data_dir,
+        "restore_backup",
+        serde_json::json!({
+            "backup_path": backup_path,
+            "target_path": target_path,
+            "target_id": target_id,
+        }),
+    )?;
+
+    let previous_mtime = fs::metadata(&target)
+        .ok()
+        .map(|m| FileTime::from_last_modification_time(&m));
+    // Both refuse while the client is running, so no safety backup is left behind
+    // for a restore that never happens
+    let safety_backup = if target.exists() {
+        Some(recorder.back_up(&target, "pre_restore")?)
+    } else {
+        recorder.snapshot(&target)?;
+        None
+    };
+
+    // Write next to the target and rename, so the client never sees a partial file
+    let tmp = target.with_extension("dat.tmp");
+    fs::write(&tmp, &data).at("write", &tmp)?;
+    fs::rename(&tmp, &target).map_err(|e| {
+        let _ = fs::remove_file(&tmp);
+        AppError::io("restore", &target, e)
+    })?;
+
+    let new_mtime = match mtime.unwrap_or_default() {
+        RestoreMtime::Now => FileTime::now(),
+        RestoreMtime::Backup => FileTime::from_unix_time(metadata.timestamp as i64, 0),
+        RestoreMtime::Keep => previous_mtime.unwrap_or_else(FileTime::now),
+    };
+    filetime::set_file_mtime(&target, new_mtime).at("set modification time of", &target)?;
+
+    Ok(RestoreResult {
+        target_path: target.to_string_lossy().into_owned(),
+        kind,
+        target_id: id,
+        sha256: sha256_of_bytes(&data),
+        verified,
+        safety_backup,
+    })
+}
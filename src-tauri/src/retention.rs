@@ -0,0 +1,590 @@
+use crate::error::{AppError, AppResult, IoResultExt};
+use crate::evesettings::{
+    app_data_dir, emit_data_changed, scan_backups, BackupEntry, Server, SettingsKind,
+};
+use crate::metadata;
+use crate::store;
+use serde::{Deserialize, Serialize};
+use std::cmp::Reverse;
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+const DAY: u64 = 86_400;
+
+/// How many backups to keep per settings file. Every rule is optional; a backup is kept
+/// if any rule selects it, and with no rules set nothing is pruned.
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq)]
+pub struct RetentionPolicy {
+    /// Most recent backups to keep.
+    pub keep_last: Option<u32>,
+    /// Newest backup of each of the last N days that have backups.
+    pub keep_daily: Option<u32>,
+    pub keep_weekly: Option<u32>,
+    pub keep_monthly: Option<u32>,
+    /// Cap on the combined size of all backups, in bytes, counting content shared by
+    /// several backups once. Oldest backups go first, but the newest backup of each
+    /// settings file is never removed by this rule.
+    pub max_total_size: Option<u64>,
+}
+
+impl RetentionPolicy {
+    fn has_keep_rules(&self) -> bool {
+        self.keep_last.is_some()
+            || self.keep_daily.is_some()
+            || self.keep_weekly.is_some()
+            || self.keep_monthly.is_some()
+    }
+}
+
+#[derive(Serialize, Debug, Clone, Copy, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum PruneReason {
+    Retention,
+    MaxTotalSize,
+}
+
+#[derive(Serialize, Debug, Clone)]
+pub struct PrunedBackup {
+    pub backup: BackupEntry,
+    /// Size of the backup's content, which other backups may share.
+    pub size: u64,
+    pub reason: PruneReason,
+    #[serde(skip)]
+    content: PathBuf,
+}
+
+impl PrunedBackup {
+    fn new(stored: StoredBackup, reason: PruneReason) -> Self {
+        PrunedBackup {
+            backup: stored.backup,
+            size: stored.size,
+            reason,
+            content: stored.content,
+        }
+    }
+
+    fn restore(self) -> StoredBackup {
+        StoredBackup {
+            backup: self.backup,
+            content: self.content,
+            size: self.size,
+        }
+    }
+}
+
+#[derive(Serialize, Debug, Clone)]
+pub struct PruneReport {
+    pub dry_run: bool,
+    pub kept_count: usize,
+    /// Disk space the kept backups use, counting shared content once.
+    pub kept_bytes: u64,
+    pub pruned: Vec<PrunedBackup>,
+    /// Disk space released: content no remaining backup points at.
+    pub freed_bytes: u64,
+    pub errors: Vec<String>,
+}
+
+fn retention_file(data_dir: &Path) -> PathBuf {
+    data_dir.join("retention.json")
+}
+
+pub(crate) fn load_policy(data_dir: &Path) -> RetentionPolicy {
+    fs::read_to_string(retention_file(data_dir))
+        .ok()
+        .and_then(|content| serde_json::from_str(&content).ok())
+        .unwrap_or_default()
+}
+
+// Proleptic Gregorian (year, month) for a day count since 1970-01-01
+fn year_month(days: i64) -> (i64, u32) {
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let doe = z.rem_euclid(146_097);
+    let yoe = (doe - doe / 1460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 } as u32;
+    let year = yoe + era * 400 + i64::from(month <= 2);
+    (year, month)
+}
+
+/// Marks the newest backup of each of the first `count` distinct buckets.
+fn keep_buckets<K: PartialEq>(
+    timestamps: &[u64],
+    count: u32,
+    bucket: impl Fn(u64) -> K,
+    keep: &mut [bool],
+) {
+    let mut last = None;
+    let mut seen = 0;
+    for (i, ts) in timestamps.iter().enumerate() {
+        if seen >= count {
+            break;
+        }
+        let b = bucket(*ts);
+        if last.as_ref() != Some(&b) {
+            keep[i] = true;
+            seen += 1;
+            last = Some(b);
+        }
+    }
+}
+
+/// Which of one file's backups (sorted newest first) the keep rules retain.
+fn select_kept(timestamps: &[u64], policy: &RetentionPolicy) -> Vec<bool> {
+    if !policy.has_keep_rules() {
+        return vec![true; timestamps.len()];
+    }
+
+    let mut keep = vec![false; timestamps.len()];
+    if let Some(n) = policy.keep_last {
+        keep.iter_mut().take(n as usize).for_each(|k| *k = true);
+    }
+    if let Some(n) = policy.keep_daily {
+        keep_buckets(timestamps, n, |ts| ts / DAY, &mut keep);
+    }
+    if let Some(n) = policy.keep_weekly {
+        // Day 0 was a Thursday; shift so weeks start on Monday
+        keep_buckets(timestamps, n, |ts| (ts / DAY + 3) / 7, &mut keep);
+    }
+    if let Some(n) = policy.keep_monthly {
+        keep_buckets(timestamps, n, |ts| year_month((ts / DAY) as i64), &mut keep);
+    }
+    keep
+}
+
+/// Backups of the same settings file: same profile folder, kind and id.
+type EntityKey = (
+    Option<Server>,
+    Option<String>,
+    PathBuf,
+    SettingsKind,
+    String,
+);
+
+fn entity_key(backup: &BackupEntry) -> EntityKey {
+    // Where the backup is kept does not matter once its server is known; without it,
+    // the backup's own folder is the best guess
+    let dir = match backup.server {
+        Some(_) => PathBuf::new(),
+        None => Path::new(&backup.path)
+            .parent()
+            .map(Path::to_path_buf)
+            .unwrap_or_default(),
+    };
+    (
+        backup.server,
+        backup.profile.clone(),
+        dir,
+        backup.kind,
+        backup.original_id.clone(),
+    )
+}
+
+/// A backup with the content it points at. Backups with identical content share one
+/// blob in the store, so sizes count once per `content`.
+struct StoredBackup {
+    backup: BackupEntry,
+    content: PathBuf,
+    size: u64,
+}
+
+fn stored_backup(backup: BackupEntry) -> StoredBackup {
+    let path = Path::new(&backup.path);
+    let content = store::content_path(path).unwrap_or_else(|_| path.to_path_buf());
+    let size = store::content_size(path);
+    StoredBackup {
+        backup,
+        content,
+        size,
+    }
+}
+
+fn unique_size<'a>(items: impl IntoIterator<Item = (&'a Path, u64)>) -> u64 {
+    let mut seen = HashSet::new();
+    items
+        .into_iter()
+        .filter(|(content, _)| seen.insert(*content))
+        .map(|(_, size)| size)
+        .sum()
+}
+
+/// Bytes freed by deleting `pruned`: content no `remaining` backup still points at.
+fn freed_bytes(pruned: &[PrunedBackup], remaining: &HashSet<&Path>) -> u64 {
+    unique_size(
+        pruned
+            .iter()
+            .map(|p| (p.content.as_path(), p.size))
+            .filter(|(content, _)| !remaining.contains(content)),
+    )
+}
+
+fn plan_prune(
+    backups: Vec<StoredBackup>,
+    policy: &RetentionPolicy,
+) -> (Vec<StoredBackup>, Vec<PrunedBackup>) {
+    let mut groups: HashMap<EntityKey, Vec<StoredBackup>> = HashMap::new();
+    for stored in backups {
+        groups
+            .entry(entity_key(&stored.backup))
+            .or_default()
+            .push(stored);
+    }
+
+    let mut kept = Vec::new();
+    let mut pruned = Vec::new();
+    let mut newest = HashSet::new();
+
+    for (_, mut group) in groups {
+        group.sort_by_key(|s| Reverse(s.backup.timestamp));
+        let timestamps: Vec<u64> = group.iter().map(|s| s.backup.timestamp).collect();
+        let keep = select_kept(&timestamps, policy);
+
+        if let Some(first) = group.first() {
+            newest.insert(first.backup.path.clone());
+        }
+        for (stored, keep) in group.into_iter().zip(keep) {
+            if keep {
+                kept.push(stored);
+            } else {
+                pruned.push(PrunedBackup::new(stored, PruneReason::Retention));
+            }
+        }
+    }
+
+    kept.sort_by_key(|s| Reverse(s.backup.timestamp));
+
+    if let Some(limit) = policy.max_total_size {
+        // Content only stops counting once the last kept backup pointing at it is gone
+        let mut users: HashMap<PathBuf, usize> = HashMap::new();
+        for stored in &kept {
+            *users.entry(stored.content.clone()).or_default() += 1;
+        }
+        let mut total = unique_size(kept.iter().map(|s| (s.content.as_path(), s.size)));
+        let mut i = kept.len();
+        while total > limit && i > 0 {
+            i -= 1;
+            if newest.contains(&kept[i].backup.path) {
+                continue;
+            }
+            let stored = kept.remove(i);
+            if let Some(count) = users.get_mut(&stored.content) {
+                *count -= 1;
+                if *count == 0 {
+                    total -= stored.size;
+                }
+            }
+            pruned.push(PrunedBackup::new(stored, PruneReason::MaxTotalSize));
+        }
+    }
+
+    pruned.sort_by_key(|p| Reverse(p.backup.timestamp));
+    (kept, pruned)
+}
+
+fn delete_backup(path: &Path) -> AppResult<()> {
+    let sidecar = metadata::sidecar_path(path);
+    fs::remove_file(path).at("delete", path)?;
+    if sidecar.exists() {
+        fs::remove_file(&sidecar).at("delete", &sidecar)?;
+    }
+    Ok(())
+}
+
+#[tauri::command]
+pub fn get_retention_policy(app: tauri::AppHandle) -> RetentionPolicy {
+    app_data_dir(&app)
+        .map(|data_dir| load_policy(&data_dir))
+        .unwrap_or_default()
+}
+
+#[tauri::command]
+pub fn set_retention_policy(app: tauri::AppHandle, policy: RetentionPolicy) -> AppResult<()> {
+    let path = retention_file(&app_data_dir(&app)?);
+    let content =
+        serde_json::to_string_pretty(&policy).map_err(|e| AppError::other(e.to_string()))?;
+    fs::write(&path, content).at("write", &path)?;
+    Ok(())
+}
+
+/// Plans `policy` over every backup and, unless `dry_run`, deletes what it prunes.
+/// Only backups `removable` accepts are pruned; the plan still sees every backup so the
+/// total size cap counts all of them.
+pub(crate) fn apply_policy(
+    data_dir: &Path,
+    custom_eve_path: Option<&str>,
+    policy: &RetentionPolicy,
+    dry_run: bool,
+    removable: impl Fn(&BackupEntry) -> bool,
+) -> AppResult<PruneReport> {
+    let backups = scan_backups(custom_eve_path)?;
+    let (mut kept, pruned) = plan_prune(backups.into_iter().map(stored_backup).collect(), policy);
+    let (mut pruned, spared): (Vec<_>, Vec<_>) =
+        pruned.into_iter().partition(|p| removable(&p.backup));
+    kept.extend(spared.into_iter().map(PrunedBackup::restore));
+
+    let mut errors = Vec::new();
+    let mut failed = Vec::new();
+    if !dry_run && !pruned.is_empty() {
+        pruned.retain(|p| match delete_backup(Path::new(&p.backup.path)) {
+            Ok(()) => true,
+            Err(e) => {
+                errors.push(e.to_string());
+                failed.push(p.content.clone());
+                false
+            }
+        });
+        let paths: Vec<&str> = pruned.iter().map(|p| p.backup.path.as_str()).collect();
+        for dir in store::store_dirs(&paths) {
+            if let Err(e) = store::collect_garbage(&dir, data_dir) {
+                errors.push(e.to_string());
+            }
+        }
+    }
+
+    let remaining: HashSet<&Path> = kept
+        .iter()
+        .map(|s| s.content.as_path())
+        .chain(failed.iter().map(PathBuf::as_path))
+        .collect();
+    Ok(PruneReport {
+        dry_run,
+        kept_count: kept.len(),
+        kept_bytes: unique_size(kept.iter().map(|s| (s.content.as_path(), s.size))),
+        freed_bytes: freed_bytes(&pruned, &remaining),
+        pruned,
+        errors,
+    })
+}
+
+/// Applies the saved retention policy (or `policy` when given) to every backup. With
+/// `dry_run` nothing is deleted and the report lists what would be. Pruning is not
+/// journaled and cannot be undone: keeping the deleted content around would free no
+/// space.
+#[tauri::command]
+pub fn prune_backups(
+    app: tauri::AppHandle,
+    custom_eve_path: Option<String>,
+    policy: Option<RetentionPolicy>,
+    dry_run: bool,
+) -> AppResult<PruneReport> {
+    let data_dir = app_data_dir(&app)?;
+    let policy = policy.unwrap_or_else(|| load_policy(&data_dir));
+    let report = apply_policy(
+        &data_dir,
+        custom_eve_path.as_deref(),
+        &policy,
+        dry_run,
+        |_| true,
+    )?;
+    if !dry_run && !report.pruned.is_empty() {
+        emit_data_changed(&app);
+    }
+    Ok(report)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // 2024-01-31 and 2024-02-01, days since 1970-01-01
+    const JAN_31: u64 = 19_753 * DAY;
+    const FEB_1: u64 = 19_754 * DAY;
+    const HOUR: u64 = 3_600;
+
+    fn policy() -> RetentionPolicy {
+        RetentionPolicy::default()
+    }
+
+    fn stored(timestamp: u64, content: &str, size: u64) -> StoredBackup {
+        StoredBackup {
+            backup: BackupEntry {
+                id: timestamp.to_string(),
+                name: "auto".to_string(),
+                path: format!("/eve/settings_Default/backups/{}.bak", timestamp),
+                timestamp,
+                kind: SettingsKind::Char,
+                original_id: "90000001".to_string(),
+                original_name: None,
+                display_name: String::new(),
+                relative_time: String::new(),
+                notes: None,
+                tags: Vec::new(),
+                server: None,
+                profile: None,
+            },
+            content: PathBuf::from(content),
+            size,
+        }
+    }
+
+    fn timestamps(backups: &[StoredBackup]) -> Vec<u64> {
+        backups.iter().map(|s| s.backup.timestamp).collect()
+    }
+
+    fn pruned_timestamps(pruned: &[PrunedBackup]) -> Vec<u64> {
+        pruned.iter().map(|p| p.backup.timestamp).collect()
+    }
+
+    #[test]
+    fn year_month_handles_month_and_leap_boundaries() {
+        assert_eq!(year_month(0), (1970, 1));
+        assert_eq!(year_month(-1), (1969, 12));
+        assert_eq!(year_month(11_016), (2000, 2));
+        assert_eq!(year_month(11_017), (2000, 3));
+        assert_eq!(year_month(19_722), (2023, 12));
+        assert_eq!(year_month(19_753), (2024, 1));
+        assert_eq!(year_month(19_754), (2024, 2));
+    }
+
+    #[test]
+    fn no_keep_rules_keeps_everything() {
+        assert_eq!(select_kept(&[3, 2, 1], &policy()), [true, true, true]);
+    }
+
+    #[test]
+    fn keep_monthly_splits_at_the_month_boundary() {
+        let ts = [
+            FEB_1 + HOUR,
+            JAN_31 + 23 * HOUR,
+            JAN_31 + 10 * HOUR,
+            JAN_31 - 40 * DAY,
+        ];
+        let keep_monthly = RetentionPolicy {
+            keep_monthly: Some(2),
+            ..policy()
+        };
+        assert_eq!(select_kept(&ts, &keep_monthly), [true, true, false, false]);
+
+        let keep_daily = RetentionPolicy {
+            keep_daily: Some(2),
+            ..policy()
+        };
+        assert_eq!(select_kept(&ts, &keep_daily), [true, true, false, false]);
+    }
+
+    #[test]
+    fn keep_last_does_not_extend_to_ties() {
+        let ts = [FEB_1, FEB_1, FEB_1, JAN_31];
+        let keep_last = RetentionPolicy {
+            keep_last: Some(2),
+            ..policy()
+        };
+        assert_eq!(select_kept(&ts, &keep_last), [true, true, false, false]);
+
+        // Backups with the same timestamp share a day, so only the first one counts
+        let keep_daily = RetentionPolicy {
+            keep_daily: Some(2),
+            ..policy()
+        };
+        assert_eq!(select_kept(&ts, &keep_daily), [true, false, false, true]);
+    }
+
+    #[test]
+    fn rules_are_combined() {
+        let ts = [FEB_1 + 2 * HOUR, FEB_1 + HOUR, JAN_31, JAN_31 - 40 * DAY];
+        let combined = RetentionPolicy {
+            keep_last: Some(1),
+            keep_monthly: Some(3),
+            ..policy()
+        };
+        assert_eq!(select_kept(&ts, &combined), [true, false, true, true]);
+    }
+
+    #[test]
+    fn total_size_counts_shared_content_once() {
+        // Newest first: a and b share content x
+        let backups = || {
+            vec![
+                stored(4, "x", 100),
+                stored(3, "x", 100),
+                stored(2, "y", 50),
+                stored(1, "z", 70),
+            ]
+        };
+
+        let capped = |limit| RetentionPolicy {
+            max_total_size: Some(limit),
+            ..policy()
+        };
+
+        let (kept, pruned) = plan_prune(backups(), &capped(220));
+        assert_eq!(timestamps(&kept), [4, 3, 2, 1]);
+        assert!(pruned.is_empty());
+
+        let (kept, pruned) = plan_prune(backups(), &capped(150));
+        assert_eq!(timestamps(&kept), [4, 3, 2]);
+        assert_eq!(pruned_timestamps(&pruned), [1]);
+        assert_eq!(pruned[0].reason, PruneReason::MaxTotalSize);
+    }
+
+    #[test]
+    fn total_size_never_removes_the_newest_backup() {
+        let backups = vec![
+            stored(4, "x", 100),
+            stored(3, "x", 100),
+            stored(2, "y", 50),
+            stored(1, "z", 70),
+        ];
+        let capped = RetentionPolicy {
+            max_total_size: Some(90),
+            ..policy()
+        };
+
+        // Dropping 3 frees nothing while 4 still points at x, and 4 is never removed
+        let (kept, pruned) = plan_prune(backups, &capped);
+        assert_eq!(timestamps(&kept), [4]);
+        assert_eq!(pruned_timestamps(&pruned), [3, 2, 1]);
+
+        let remaining: HashSet<&Path> = kept.iter().map(|s| s.content.as_path()).collect();
+        assert_eq!(freed_bytes(&pruned, &remaining), 120);
+    }
+
+    #[test]
+    fn moved_backups_share_one_keep_budget() {
+        let mut backups = vec![stored(3, "x", 1), stored(2, "y", 1), stored(1, "z", 1)];
+        for (i, stored) in backups.iter_mut().enumerate() {
+            stored.backup.server = Some(Server::Tranquility);
+            stored.backup.profile = Some("Default".to_string());
+            if i > 0 {
+                stored.backup.path = format!("/root/tq/settings_Default/backups/{}.bak", i);
+            }
+        }
+        let keep_last = RetentionPolicy {
+            keep_last: Some(2),
+            ..policy()
+        };
+
+        let (kept, pruned) = plan_prune(backups, &keep_last);
+        assert_eq!(timestamps(&kept), [3, 2]);
+        assert_eq!(pruned_timestamps(&pruned), [1]);
+    }
+
+    #[test]
+    fn keep_rules_run_before_the_size_cap() {
+        let backups = vec![
+            stored(FEB_1, "x", 100),
+            stored(JAN_31, "y", 100),
+            stored(JAN_31 - 40 * DAY, "z", 100),
+        ];
+        let both = RetentionPolicy {
+            keep_last: Some(2),
+            max_total_size: Some(150),
+            ..policy()
+        };
+
+        let (kept, pruned) = plan_prune(backups, &both);
+        assert_eq!(timestamps(&kept), [FEB_1]);
+        assert_eq!(
+            pruned
+                .iter()
+                .map(|p| (p.backup.timestamp, p.reason))
+                .collect::<Vec<_>>(),
+            [
+                (JAN_31, PruneReason::MaxTotalSize),
+                (JAN_31 - 40 * DAY, PruneReason::Retention),
+            ]
+        );
+    }
+}
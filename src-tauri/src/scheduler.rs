@@ -0,0 +1,309 @@
+use crate::error::{AppError, AppResult, IoResultExt};
+use crate::evesettings::{
+    app_data_dir, backup_settings_file, emit_data_changed, scan_backups, scan_installations,
+    sha256_of_file, BackupEntry,
+};
+use crate::location;
+use crate::retention;
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Mutex, MutexGuard, PoisonError};
+use std::thread;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+use tauri::Manager;
+
+const AUTO_BACKUP_NAME: &str = "auto";
+const TICK: Duration = Duration::from_secs(30);
+
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq)]
+pub struct BackupSchedule {
+    pub enabled: bool,
+    /// Minutes between runs; `None` or 0 disables interval backups.
+    pub interval_minutes: Option<u32>,
+    pub on_startup: bool,
+    /// Settings files to back up; empty means every core_*.dat found.
+    pub paths: Vec<String>,
+    pub custom_eve_path: Option<String>,
+}
+
+#[derive(Serialize, Debug, Clone)]
+pub struct ScheduledRunResult {
+    pub timestamp: u64,
+    pub created: Vec<BackupEntry>,
+    pub unchanged_count: usize,
+    /// Earlier automatic backups the retention policy removed after the run.
+    pub pruned_count: usize,
+    pub errors: Vec<String>,
+}
+
+#[derive(Default)]
+pub struct BackupScheduler {
+    schedule: Mutex<BackupSchedule>,
+    /// Set while a run is in progress, so a manual run and the timer never overlap.
+    running: AtomicBool,
+    /// sha256 of each settings file as of its last snapshot.
+    last_hashes: Mutex<HashMap<PathBuf, String>>,
+    last_run: Mutex<Option<ScheduledRunResult>>,
+}
+
+// A panic during one run must not take the scheduler down with it
+fn lock<T>(mutex: &Mutex<T>) -> MutexGuard<'_, T> {
+    mutex.lock().unwrap_or_else(PoisonError::into_inner)
+}
+
+/// Clears the running flag when a run ends, however it ends.
+struct RunGuard<'a>(&'a AtomicBool);
+
+impl Drop for RunGuard<'_> {
+    fn drop(&mut self) {
+        self.0.store(false, Ordering::Release);
+    }
+}
+
+fn schedule_file(app: &tauri::AppHandle) -> AppResult<PathBuf> {
+    Ok(app_data_dir(app)?.join("backup_schedule.json"))
+}
+
+fn load_schedule(app: &tauri::AppHandle) -> BackupSchedule {
+    schedule_file(app)
+        .ok()
+        .and_then(|path| fs::read_to_string(path).ok())
+        .and_then(|content| serde_json::from_str(&content).ok())
+        .unwrap_or_default()
+}
+
+fn scheduled_sources(schedule: &BackupSchedule) -> AppResult<Vec<PathBuf>> {
+    if !schedule.paths.is_empty() {
+        return Ok(schedule.paths.iter().map(PathBuf::from).collect());
+    }
+
+    let (server_profiles, _) = scan_installations(schedule.custom_eve_path.as_deref())?;
+    let mut sources: Vec<PathBuf> = server_profiles
+        .values()
+        .flatten()
+        .flat_map(|p| p.accounts.iter().chain(p.characters.iter()))
+        .map(|entry| PathBuf::from(&entry.path))
+        .collect();
+    sources.sort();
+    Ok(sources)
+}
+
+/// Hash of the newest existing backup of `source`, so unchanged files are skipped even
+/// on the first run after the app starts.
+fn newest_backup_hash(source: &Path, backups: &[BackupEntry]) -> Option<String> {
+    let backup_dirs = [
+        source.parent()?.join("backups"),
+        location::backup_dir_for(source).ok()?,
+    ];
+    let filename = source.file_name()?.to_str()?;
+    let stem = filename.strip_prefix("core_")?.strip_suffix(".dat")?;
+
+    backups
+        .iter()
+        .filter(|b| {
+            Path::new(&b.path)
+                .parent()
+                .is_some_and(|dir| backup_dirs.iter().any(|d| d == dir))
+        })
+        .filter(|b| stem == format!("{}_{}", b.kind.file_tag(), b.original_id))
+        .max_by_key(|b| b.timestamp)
+        .and_then(|b| sha256_of_file(Path::new(&b.path)).ok())
+}
+
+fn run_backups(app: &tauri::AppHandle, scheduler: &BackupScheduler) -> ScheduledRunResult {
+    let mut result = ScheduledRunResult {
+        timestamp: SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |d| d.as_secs()),
+        created: Vec::new(),
+        unchanged_count: 0,
+        pruned_count: 0,
+        errors: Vec::new(),
+    };
+
+    if scheduler.running.swap(true, Ordering::AcqRel) {
+        result
+            .errors
+            .push("A scheduled backup is already running".to_string());
+        return result;
+    }
+    let guard = RunGuard(&scheduler.running);
+    backup_sources(scheduler, &mut result);
+    if !result.created.is_empty() {
+        apply_retention(app, scheduler, &mut result);
+        emit_data_changed(app);
+    }
+    drop(guard);
+
+    *lock(&scheduler.last_run) = Some(result.clone());
+    result
+}
+
+fn backup_sources(scheduler: &BackupScheduler, result: &mut ScheduledRunResult) {
+    let schedule = lock(&scheduler.schedule).clone();
+
+    let sources = match scheduled_sources(&schedule) {
+        Ok(sources) => sources,
+        Err(e) => {
+            result.errors.push(e.to_string());
+            return;
+        }
+    };
+
+    let mut backups: Option<Vec<BackupEntry>> = None;
+
+    for source in sources {
+        let hash = match sha256_of_file(&source) {
+            Ok(hash) => hash,
+            Err(e) => {
+                result.errors.push(e.to_string());
+                continue;
+            }
+        };
+
+        let mut previous = lock(&scheduler.last_hashes).get(&source).cloned();
+        if previous.is_none() {
+            let backups = backups.get_or_insert_with(|| {
+                scan_backups(schedule.custom_eve_path.as_deref()).unwrap_or_default()
+            });
+            previous = newest_backup_hash(&source, backups);
+        }
+
+        if previous.as_ref() == Some(&hash) {
+            lock(&scheduler.last_hashes).insert(source, hash);
+            result.unchanged_count += 1;
+            continue;
+        }
+
+        match backup_settings_file(&source, AUTO_BACKUP_NAME, None) {
+            Ok(entry) => {
+                lock(&scheduler.last_hashes).insert(source, hash);
+                result.created.push(entry);
+            }
+            Err(e) => result
+                .errors
+                .push(format!("Failed to back up {}: {}", source.display(), e)),
+        }
+    }
+}
+
+/// Applies the saved retention policy to the scheduler's own backups in the folders the
+/// run wrote to. Named and safety backups are only ever pruned by an explicit prune.
+fn apply_retention(
+    app: &tauri::AppHandle,
+    scheduler: &BackupScheduler,
+    result: &mut ScheduledRunResult,
+) {
+    let data_dir = match app_data_dir(app) {
+        Ok(data_dir) => data_dir,
+        Err(e) => {
+            result.errors.push(e.to_string());
+            return;
+        }
+    };
+    let policy = retention::load_policy(&data_dir);
+    if policy == retention::RetentionPolicy::default() {
+        return;
+    }
+
+    let scope: HashSet<&Path> = result
+        .created
+        .iter()
+        .filter_map(|entry| Path::new(&entry.path).parent())
+        .collect();
+    let custom_eve_path = lock(&scheduler.schedule).custom_eve_path.clone();
+    let removable = |backup: &BackupEntry| {
+        backup.name == AUTO_BACKUP_NAME
+            && Path::new(&backup.path)
+                .parent()
+                .is_some_and(|dir| scope.contains(dir))
+    };
+    match retention::apply_policy(
+        &data_dir,
+        custom_eve_path.as_deref(),
+        &policy,
+        false,
+        removable,
+    ) {
+        Ok(report) => {
+            result.pruned_count = report.pruned.len();
+            result.errors.extend(report.errors);
+        }
+        Err(e) => result.errors.push(e.to_string()),
+    }
+}
+
+/// Registers the scheduler state and starts the background thread that runs it.
+pub fn init(app: &tauri::AppHandle) {
+    app.manage(BackupScheduler {
+        schedule: Mutex::new(load_schedule(app)),
+        ..Default::default()
+    });
+
+    let app = app.clone();
+    thread::spawn(move || {
+        let scheduler = app.state::<BackupScheduler>();
+        let mut last = Instant::now();
+
+        let startup = lock(&scheduler.schedule).clone();
+        if startup.enabled && startup.on_startup {
+            run_backups(&app, &scheduler);
+        }
+
+        loop {
+            thread::sleep(TICK);
+
+            let schedule = lock(&scheduler.schedule).clone();
+            let interval = match schedule.interval_minutes {
+                Some(minutes) if schedule.enabled && minutes > 0 => {
+                    Duration::from_secs(u64::from(minutes) * 60)
+                }
+                _ => continue,
+            };
+
+            if last.elapsed() >= interval {
+                run_backups(&app, &scheduler);
+                last = Instant::now();
+            }
+        }
+    });
+}
+
+#[tauri::command]
+pub fn get_backup_schedule(scheduler: tauri::State<'_, BackupScheduler>) -> BackupSchedule {
+    lock(&scheduler.schedule).clone()
+}
+
+#[tauri::command]
+pub fn set_backup_schedule(
+    app: tauri::AppHandle,
+    scheduler: tauri::State<'_, BackupScheduler>,
+    schedule: BackupSchedule,
+) -> AppResult<()> {
+    let path = schedule_file(&app)?;
+    let content =
+        serde_json::to_string_pretty(&schedule).map_err(|e| AppError::other(e.to_string()))?;
+    fs::write(&path, content).at("write", &path)?;
+
+    *lock(&scheduler.schedule) = schedule;
+    Ok(())
+}
+
+/// Runs the configured backups immediately, regardless of the timer.
+#[tauri::command]
+pub fn run_scheduled_backup(
+    app: tauri::AppHandle,
+    scheduler: tauri::State<'_, BackupScheduler>,
+) -> ScheduledRunResult {
+    run_backups(&app, &scheduler)
+}
+
+#[tauri::command]
+pub fn get_last_scheduled_run(
+    scheduler: tauri::State<'_, BackupScheduler>,
+) -> Option<ScheduledRunResult> {
+    lock(&scheduler.last_run).clone()
+}
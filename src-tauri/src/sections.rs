@@ -0,0 +1,335 @@
+use crate::error::{AppError, AppResult};
+use crate::marshal::Value;
+use serde::Serialize;
+
+#[derive(Serialize, Debug)]
+pub struct KeyRule {
+    pub group: &'static str,
+    /// Setting keys covered by the rule; an empty list covers the whole group.
+    pub prefixes: &'static [&'static str],
+}
+
+impl KeyRule {
+    fn matches(&self, key: &Value) -> bool {
+        key.as_str().is_some_and(|k| self.matches_key(k))
+    }
+
+    fn matches_key(&self, key: &str) -> bool {
+        self.prefixes.is_empty() || self.prefixes.iter().any(|p| key.starts_with(p))
+    }
+
+    pub fn covers(&self, group: &str, key: &str) -> bool {
+        self.group == group && self.matches_key(key)
+    }
+}
+
+#[derive(Serialize, Debug)]
+pub struct SectionDef {
+    pub id: &'static str,
+    pub label: &'static str,
+    pub description: &'static str,
+    pub rules: &'static [KeyRule],
+}
+
+const fn whole(group: &'static str) -> KeyRule {
+    KeyRule {
+        group,
+        prefixes: &[],
+    }
+}
+
+pub const SECTIONS: &[SectionDef] = &[
+    SectionDef {
+        id: "overview",
+        label: "Overview",
+        description: "Overview tabs, profiles, columns, colors and bracket filters",
+        rules: &[whole("overview")],
+    },
+    SectionDef {
+        id: "windows",
+        label: "Window positions",
+        description: "Window sizes, positions, stacks and which windows are open",
+        rules: &[whole("windows")],
+    },
+    SectionDef {
+        id: "shortcuts",
+        label: "Keyboard shortcuts",
+        description: "Custom key bindings for client commands",
+        rules: &[whole("cmd")],
+    },
+    SectionDef {
+        id: "audio",
+        label: "Audio",
+        description: "Volume levels and sound toggles",
+        rules: &[whole("audio")],
+    },
+    SectionDef {
+        id: "drones",
+        label: "Drones",
+        description: "Drone groups and drone window settings",
+        rules: &[KeyRule {
+            group: "ui",
+            prefixes: &["drone", "Drone"],
+        }],
+    },
+    SectionDef {
+        id: "dscan",
+        label: "Directional scan",
+        description: "D-scan presets, range and angle",
+        rules: &[KeyRule {
+            group: "ui",
+            prefixes: &["dscan", "directionalScan", "scanner"],
+        }],
+    },
+    SectionDef {
+        id: "notifications",
+        label: "Notifications",
+        description: "Notification popups and sound alerts",
+        rules: &[whole("notifications")],
+    },
+    SectionDef {
+        id: "neocom",
+        label: "Neocom",
+        description: "Neocom buttons, order and position",
+        rules: &[KeyRule {
+            group: "ui",
+            prefixes: &["neocom"],
+        }],
+    },
+];
+
+pub fn find_section(id: &str) -> Option<&'static SectionDef> {
+    SECTIONS.iter().find(|s| s.id == id)
+}
+
+// The settings store is a dict of group name -> dict of setting key -> value. Some
+// client builds wrap it in a tuple together with a format version.
+pub fn groups(root: &Value) -> Option<&Vec<(Value, Value)>> {
+    match root.resolve() {
+        Value::Dict(entries) => Some(entries),
+        Value::Tuple(items) | Value::List(items) => items.iter().find_map(Value::as_dict),
+        _ => None,
+    }
+}
+
+pub fn groups_mut(root: &mut Value) -> Option<&mut Vec<(Value, Value)>> {
+    match root.resolve_mut() {
+        Value::Dict(entries) => Some(entries),
+        Value::Tuple(items) | Value::List(items) => {
+            items.iter_mut().find_map(|item| item.as_dict_mut())
+        }
+        _ => None,
+    }
+}
+
+fn group<'a>(groups: &'a [(Value, Value)], name: &str) -> Option<&'a (Value, Value)> {
+    groups.iter().find(|(k, _)| k.as_str() == Some(name))
+}
+
+/// Entries of the named settings group, if present and a dict.
+pub fn group_dict<'a>(root: &'a Value, name: &str) -> Option<&'a Vec<(Value, Value)>> {
+    group(groups(root)?, name)?.1.as_dict()
+}
+
+/// Sets `group.key` to `value`, creating the group if needed. Returns whether
+/// anything changed.
+pub fn set_entry(root: &mut Value, group: &str, key: &str, value: Value) -> AppResult<bool> {
+    let groups =
+        groups_mut(root).ok_or_else(|| AppError::invalid_data("Settings have no group table"))?;
+
+    let index = match groups.iter().position(|(k, _)| k.as_str() == Some(group)) {
+        Some(i) => i,
+        None => {
+            groups.push((Value::Str(group.to_string()), Value::Dict(Vec::new())));
+            groups.len() - 1
+        }
+    };
+    let entries = groups[index].1.as_dict_mut().ok_or_else(|| {
+        AppError::invalid_data(format!("Settings group '{}' is not a dict", group))
+    })?;
+
+    match entries.iter_mut().find(|(k, _)| k.as_str() == Some(key)) {
+        Some(entry) if entry.1.detached() == value => Ok(false),
+        Some(entry) => {
+            entry.1 = value;
+            Ok(true)
+        }
+        None => {
+            entries.push((Value::Str(key.to_string()), value));
+            Ok(true)
+        }
+    }
+}
+
+/// Makes `target` hold exactly the source's keys for every rule of `section`: keys are
+/// added or overwritten from `source`, covered keys the source lacks are removed, and
+/// everything the section does not cover is left untouched. Returns the number of
+/// keys that changed.
+pub fn copy_section(source: &Value, target: &mut Value, section: &SectionDef) -> AppResult<usize> {
+    let source_groups = groups(source)
+        .ok_or_else(|| AppError::invalid_data("Source settings have no group table"))?;
+    let target_groups = groups_mut(target)
+        .ok_or_else(|| AppError::invalid_data("Target settings have no group table"))?;
+    let mut changed = 0;
+
+    for rule in section.rules {
+        let source_group = group(source_groups, rule.group);
+        let entries: Vec<(Value, Value)> = source_group
+            .and_then(|(_, g)| g.as_dict())
+            .map(|g| {
+                g.iter()
+                    .filter(|(k, _)| rule.matches(k))
+                    .map(|(k, v)| (k.detached(), v.detached()))
+                    .collect()
+            })
+            .unwrap_or_default();
+
+        let index = match target_groups
+            .iter()
+            .position(|(k, _)| k.as_str() == Some(rule.group))
+        {
+            Some(i) => i,
+            None => match source_group {
+                Some((key, _)) if !entries.is_empty() => {
+                    target_groups.push((key.detached(), Value::Dict(Vec::new())));
+                    target_groups.len() - 1
+                }
+                _ => continue,
+            },
+        };
+
+        let target_group = target_groups[index].1.as_dict_mut().ok_or_else(|| {
+            AppError::invalid_data(format!("Settings group '{}' is not a dict", rule.group))
+        })?;
+
+        let before = target_group.len();
+        target_group.retain(|(k, _)| {
+            !rule.matches(k) || entries.iter().any(|(sk, _)| sk.as_str() == k.as_str())
+        });
+        changed += before - target_group.len();
+
+        for (key, value) in entries {
+            match target_group
+                .iter_mut()
+                .find(|(k, _)| k.as_str() == key.as_str())
+            {
+                Some(entry) => {
+                    if entry.1.detached() != value {
+                        entry.1 = value;
+                        changed += 1;
+                    }
+                }
+                None => {
+                    target_group.push((key, value));
+                    changed += 1;
+                }
+            }
+        }
+    }
+
+    Ok(changed)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::marshal;
+
+    fn fixture() -> Value {
+        let data = include_bytes!("../tests/fixtures/core_char_90000001.dat");
+        marshal::decode(data).unwrap().root.detached()
+    }
+
+    fn entry(key: &str, value: i64) -> (Value, Value) {
+        (Value::Str(key.to_string()), Value::Int(value))
+    }
+
+    fn group_entries<'a>(root: &'a Value, name: &str) -> &'a Vec<(Value, Value)> {
+        group_dict(root, name).unwrap()
+    }
+
+    fn group_entries_mut<'a>(root: &'a mut Value, name: &str) -> &'a mut Vec<(Value, Value)> {
+        let groups = groups_mut(root).unwrap();
+        let (_, group) = groups
+            .iter_mut()
+            .find(|(k, _)| k.as_str() == Some(name))
+            .unwrap();
+        group.as_dict_mut().unwrap()
+    }
+
+    #[test]
+    fn whole_group_is_replaced_and_other_groups_are_untouched() {
+        let source = fixture();
+        let mut target = fixture();
+        let audio = group_entries_mut(&mut target, "audio");
+        assert!(!audio.is_empty());
+        audio[0].1 = Value::Str("edited".to_string());
+        audio.push(entry("onlyInTarget", 1));
+        *group_entries_mut(&mut target, "cmd") = vec![entry("onlyInTarget", 2)];
+        let before = target.clone();
+
+        let changed = copy_section(&source, &mut target, find_section("audio").unwrap()).unwrap();
+        assert_eq!(changed, 2);
+        assert_eq!(
+            group_entries(&target, "audio"),
+            group_entries(&source, "audio")
+        );
+        for name in ["windows", "cmd", "overview"] {
+            assert_eq!(group_entries(&target, name), group_entries(&before, name));
+        }
+    }
+
+    #[test]
+    fn prefix_rule_replaces_only_covered_keys() {
+        let mut source = fixture();
+        groups_mut(&mut source).unwrap().push((
+            Value::Str("ui".to_string()),
+            Value::Dict(vec![
+                entry("droneGroupA", 1),
+                entry("droneGroupB", 2),
+                entry("neocomAlign", 3),
+            ]),
+        ));
+        let mut target = fixture();
+        groups_mut(&mut target).unwrap().push((
+            Value::Str("ui".to_string()),
+            Value::Dict(vec![
+                entry("uiScale", 9),
+                entry("droneGroupA", 5),
+                entry("DroneWindowOpen", 1),
+                entry("neocomAlign", 7),
+            ]),
+        ));
+
+        let changed = copy_section(&source, &mut target, find_section("drones").unwrap()).unwrap();
+        // DroneWindowOpen removed, droneGroupA replaced, droneGroupB added
+        assert_eq!(changed, 3);
+        assert_eq!(
+            group_entries(&target, "ui"),
+            &vec![
+                entry("uiScale", 9),
+                entry("droneGroupA", 1),
+                entry("neocomAlign", 7),
+                entry("droneGroupB", 2),
+            ]
+        );
+        assert_eq!(
+            copy_section(&source, &mut target, find_section("drones").unwrap()).unwrap(),
+            0
+        );
+    }
+
+    #[test]
+    fn missing_source_group_clears_covered_keys() {
+        let source = fixture();
+        let mut target = fixture();
+        groups_mut(&mut target).unwrap().push((
+            Value::Str("ui".to_string()),
+            Value::Dict(vec![entry("dscanRange", 14), entry("uiScale", 9)]),
+        ));
+
+        let changed = copy_section(&source, &mut target, find_section("dscan").unwrap()).unwrap();
+        assert_eq!(changed, 1);
+        assert_eq!(group_entries(&target, "ui"), &vec![entry("uiScale", 9)]);
+    }
+}
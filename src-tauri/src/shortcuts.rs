@@ -0,0 +1,463 @@
+use crate::clients;
+use crate::error::{AppError, AppResult, IoResultExt};
+use crate::evesettings::{
+    app_data_dir, emit_data_changed, read_editable_settings, read_settings_document,
+    scan_installations, write_settings_document, Server, TargetResult,
+};
+use crate::marshal::Value;
+use crate::operations::OperationRecorder;
+use crate::sections;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+const SHORTCUT_GROUP: &str = "cmd";
+
+// Shortcuts are stored as tuples of Windows virtual-key codes, modifiers first
+const KEY_NAMES: &[(i64, &str)] = &[
+    (8, "Backspace"),
+    (9, "Tab"),
+    (13, "Enter"),
+    (16, "Shift"),
+    (17, "Ctrl"),
+    (18, "Alt"),
+    (19, "Pause"),
+    (20, "CapsLock"),
+    (27, "Esc"),
+    (32, "Space"),
+    (33, "PageUp"),
+    (34, "PageDown"),
+    (35, "End"),
+    (36, "Home"),
+    (37, "Left"),
+    (38, "Up"),
+    (39, "Right"),
+    (40, "Down"),
+    (44, "PrintScreen"),
+    (45, "Insert"),
+    (46, "Delete"),
+    (106, "NumMultiply"),
+    (107, "NumPlus"),
+    (109, "NumMinus"),
+    (110, "NumDecimal"),
+    (111, "NumDivide"),
+    (144, "NumLock"),
+    (145, "ScrollLock"),
+    (186, ";"),
+    (187, "="),
+    (188, ","),
+    (189, "-"),
+    (190, "."),
+    (191, "/"),
+    (192, "`"),
+    (219, "["),
+    (220, "\\"),
+    (221, "]"),
+    (222, "'"),
+];
+
+// Modifiers in the order they are written, so the same combo always reads the same way
+const MODIFIERS: &[i64] = &[17, 16, 18];
+
+fn key_name(code: i64) -> String {
+    match code {
+        48..=57 | 65..=90 => ((code as u8) as char).to_string(),
+        96..=105 => format!("Num{}", code - 96),
+        112..=135 => format!("F{}", code - 111),
+        _ => KEY_NAMES
+            .iter()
+            .find(|(c, _)| *c == code)
+            .map(|(_, name)| name.to_string())
+            .unwrap_or_else(|| format!("VK_{}", code)),
+    }
+}
+
+fn key_code(name: &str) -> Option<i64> {
+    if let Some((code, _)) = KEY_NAMES.iter().find(|(_, n)| n.eq_ignore_ascii_case(name)) {
+        return Some(*code);
+    }
+    if let Some(code) = name.strip_prefix("VK_") {
+        return code.parse().ok();
+    }
+    if let Some(n) = name.strip_prefix("Num").and_then(|n| n.parse::<i64>().ok()) {
+        return (0..=9).contains(&n).then_some(96 + n);
+    }
+    if let Some(n) = name.strip_prefix('F').and_then(|n| n.parse::<i64>().ok()) {
+        return (1..=24).contains(&n).then_some(111 + n);
+    }
+
+    let mut chars = name.chars();
+    match (chars.next(), chars.next()) {
+        (Some(c), None) if c.is_ascii_alphanumeric() => Some(c.to_ascii_uppercase() as i64),
+        _ => None,
+    }
+}
+
+/// Modifiers first in `MODIFIERS` order, other keys after them as given.
+fn normalized(codes: &[i64]) -> Vec<i64> {
+    let mut codes = codes.to_vec();
+    codes.sort_by_key(|c| {
+        MODIFIERS
+            .iter()
+            .position(|m| m == c)
+            .unwrap_or(MODIFIERS.len())
+    });
+    codes
+}
+
+fn format_combo(codes: &[i64]) -> String {
+    normalized(codes)
+        .iter()
+        .map(|c| key_name(*c))
+        .collect::<Vec<_>>()
+        .join("+")
+}
+
+fn parse_combo(combo: &str) -> AppResult<Vec<i64>> {
+    if combo.trim().is_empty() {
+        return Ok(Vec::new());
+    }
+    let codes = combo
+        .split('+')
+        .map(|part| {
+            key_code(part.trim()).ok_or_else(|| {
+                AppError::invalid_input(format!("Unknown key '{}' in '{}'", part, combo))
+            })
+        })
+        .collect::<AppResult<Vec<_>>>()?;
+    Ok(normalized(&codes))
+}
+
+/// Key codes bound to a command, an empty list for an unbound command, or `None` when
+/// the stored value does not look like a shortcut at all.
+fn shortcut_codes(value: &Value) -> Option<Vec<i64>> {
+    match value.resolve() {
+        Value::None => Some(Vec::new()),
+        Value::Tuple(items) | Value::List(items) => items.iter().map(Value::as_int).collect(),
+        _ => None,
+    }
+}
+
+fn read_shortcuts(root: &Value) -> BTreeMap<String, Vec<i64>> {
+    sections::group_dict(root, SHORTCUT_GROUP)
+        .map(|entries| {
+            entries
+                .iter()
+                .filter_map(|(k, v)| Some((k.as_str()?.to_string(), shortcut_codes(v)?)))
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+/// Human-editable shortcut file: command name -> "Ctrl+Shift+F1", "" when unbound.
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+pub struct ShortcutFile {
+    pub shortcuts: BTreeMap<String, String>,
+}
+
+fn is_toml(path: &Path) -> bool {
+    path.extension()
+        .and_then(|e| e.to_str())
+        .is_some_and(|e| e.eq_ignore_ascii_case("toml"))
+}
+
+fn write_shortcut_file(path: &Path, file: &ShortcutFile) -> AppResult<()> {
+    let content = if is_toml(path) {
+        toml::to_string_pretty(file).map_err(|e| AppError::other(e.to_string()))?
+    } else {
+        serde_json::to_string_pretty(file).map_err(|e| AppError::other(e.to_string()))?
+    };
+    fs::write(path, content).at("write", path)
+}
+
+fn read_shortcut_file(path: &Path) -> AppResult<ShortcutFile> {
+    let content = fs::read_to_string(path).at("read", path)?;
+    let invalid =
+        |e: &dyn std::fmt::Display| AppError::invalid_data(format!("Invalid shortcut file: {}", e));
+    if is_toml(path) {
+        toml::from_str(&content).map_err(|e| invalid(&e).with_path(path))
+    } else {
+        serde_json::from_str(&content).map_err(|e| invalid(&e).with_path(path))
+    }
+}
+
+fn apply_shortcuts(root: &mut Value, bindings: &BTreeMap<String, Vec<i64>>) -> AppResult<usize> {
+    let mut changed = 0;
+
+    for (command, codes) in bindings {
+        let current = sections::group_dict(root, SHORTCUT_GROUP).and_then(|entries| {
+            entries
+                .iter()
+                .find(|(k, _)| k.as_str() == Some(command))
+                .map(|(_, v)| v.resolve().clone())
+        });
+        // Only the written order differs; keep the client's
+        if current
+            .as_ref()
+            .and_then(shortcut_codes)
+            .is_some_and(|stored| normalized(&stored) == *codes)
+        {
+            continue;
+        }
+
+        let value = if codes.is_empty() {
+            Value::None
+        } else {
+            let items = codes.iter().map(|c| Value::Int(*c)).collect();
+            match current {
+                Some(Value::List(_)) => Value::List(items),
+                _ => Value::Tuple(items),
+            }
+        };
+
+        if sections::set_entry(root, SHORTCUT_GROUP, command, value)? {
+            changed += 1;
+        }
+    }
+
+    Ok(changed)
+}
+
+fn import_shortcuts_into(
+    target: &Path,
+    bindings: &BTreeMap<String, Vec<i64>>,
+    recorder: &mut OperationRecorder,
+) -> AppResult<()> {
+    let mut doc = read_editable_settings(target)?;
+
+    if apply_shortcuts(&mut doc.root, bindings).map_err(|e| e.with_path(target))? > 0 {
+        recorder.back_up(target, "pre_shortcuts")?;
+        write_settings_document(target, &doc)?;
+    }
+    Ok(())
+}
+
+#[derive(Serialize, Debug, Clone)]
+pub struct ShortcutConflict {
+    pub keys: String,
+    pub commands: Vec<String>,
+}
+
+#[derive(Serialize, Debug, Clone)]
+pub struct ShortcutReport {
+    pub path: String,
+    pub id: String,
+    pub server: Server,
+    pub profile: String,
+    pub bound_count: usize,
+    pub conflicts: Vec<ShortcutConflict>,
+    pub unbound: Vec<String>,
+    pub error: Option<AppError>,
+}
+
+fn find_conflicts(shortcuts: &BTreeMap<String, Vec<i64>>) -> Vec<ShortcutConflict> {
+    let mut by_combo: BTreeMap<String, Vec<String>> = BTreeMap::new();
+    for (command, codes) in shortcuts {
+        if !codes.is_empty() {
+            by_combo
+                .entry(format_combo(codes))
+                .or_default()
+                .push(command.clone());
+        }
+    }
+
+    by_combo
+        .into_iter()
+        .filter(|(_, commands)| commands.len() > 1)
+        .map(|(keys, commands)| ShortcutConflict { keys, commands })
+        .collect()
+}
+
+#[tauri::command]
+pub fn export_shortcuts(source_path: String, export_path: String) -> AppResult<usize> {
+    let doc = read_settings_document(Path::new(&source_path))?;
+    let shortcuts = read_shortcuts(&doc.root);
+
+    if shortcuts.is_empty() {
+        return Err(AppError::invalid_data(
+            "No keyboard shortcuts found in this file",
+        ));
+    }
+
+    let file = ShortcutFile {
+        shortcuts: shortcuts
+            .iter()
+            .map(|(command, codes)| (command.clone(), format_combo(codes)))
+            .collect(),
+    };
+    write_shortcut_file(Path::new(&export_path), &file)?;
+
+    Ok(file.shortcuts.len())
+}
+
+#[tauri::command]
+pub fn import_shortcuts(
+    app: tauri::AppHandle,
+    import_path: String,
+    target_paths: Vec<String>,
+) -> AppResult<Vec<TargetResult>> {
+    let results = import_shortcut_files(&app_data_dir(&app)?, import_path, target_paths)?;
+    if results.iter().any(|r| r.error.is_none()) {
+        emit_data_changed(&app);
+    }
+    Ok(results)
+}
+
+pub(crate) fn import_shortcut_files(
+    data_dir: &Path,
+    import_path: String,
+    target_paths: Vec<String>,
+) -> AppResult<Vec<TargetResult>> {
+    let file = read_shortcut_file(Path::new(&import_path))?;
+    let bindings = file
+        .shortcuts
+        .iter()
+        .map(|(command, combo)| Ok((command.clone(), parse_combo(combo)?)))
+        .collect::<AppResult<BTreeMap<_, _>>>()?;
+    clients::ensure_writable(&target_paths)?;
+
+    let mut recorder = OperationRecorder::begin(
+        data_dir,
+        "import_shortcuts",
+        serde_json::json!({ "import_path": import_path, "target_paths": target_paths }),
+    )?;
+    let mut results = Vec::with_capacity(target_paths.len());
+
+    for target_path in target_paths {
+        let target = PathBuf::from(&target_path);
+        let error = import_shortcuts_into(&target, &bindings, &mut recorder).err();
+        results.push(TargetResult {
+            path: target_path,
+            error,
+        });
+    }
+
+    Ok(results)
+}
+
+#[tauri::command]
+pub fn check_shortcuts(custom_eve_path: Option<String>) -> AppResult<Vec<ShortcutReport>> {
+    let (server_profiles, _) = scan_installations(custom_eve_path.as_deref())?;
+    let mut reports = Vec::new();
+
+    for profiles in server_profiles.values() {
+        for account in profiles.iter().flat_map(|p| p.accounts.iter()) {
+            let mut report = ShortcutReport {
+                path: account.path.clone(),
+                id: account.id.clone(),
+                server: account.server,
+                profile: account.profile.clone(),
+                bound_count: 0,
+                conflicts: Vec::new(),
+                unbound: Vec::new(),
+                error: None,
+            };
+
+            match read_settings_document(Path::new(&account.path)) {
+                Ok(doc) => {
+                    let shortcuts = read_shortcuts(&doc.root);
+                    report.bound_count = shortcuts.values().filter(|c| !c.is_empty()).count();
+                    report.conflicts = find_conflicts(&shortcuts);
+                    report.unbound = shortcuts
+                        .iter()
+                        .filter(|(_, codes)| codes.is_empty())
+                        .map(|(command, _)| command.clone())
+                        .collect();
+                }
+                Err(e) => report.error = Some(e),
+            }
+
+            reports.push(report);
+        }
+    }
+
+    reports.sort_by(|a, b| a.path.cmp(&b.path));
+    Ok(reports)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn bindings(entries: &[(&str, &[i64])]) -> BTreeMap<String, Vec<i64>> {
+        entries
+            .iter()
+            .map(|(command, codes)| (command.to_string(), codes.to_vec()))
+            .collect()
+    }
+
+    #[test]
+    fn key_names_and_codes_agree() {
+        for code in (8..=255).filter(|c| !key_name(*c).starts_with("VK_")) {
+            assert_eq!(key_code(&key_name(code)), Some(code), "{}", key_name(code));
+        }
+        assert_eq!(key_code("a"), Some(65));
+        assert_eq!(key_code("esc"), Some(27));
+        assert_eq!(key_code("F12"), Some(123));
+        assert_eq!(key_code("F25"), None);
+        assert_eq!(key_code("Num7"), Some(103));
+        assert_eq!(key_code("VK_255"), Some(255));
+        assert_eq!(key_code("Hyper"), None);
+    }
+
+    #[test]
+    fn combos_parse_in_any_modifier_order() {
+        assert_eq!(parse_combo("Ctrl+Shift+X").unwrap(), [17, 16, 88]);
+        assert_eq!(parse_combo("Shift+Ctrl+X").unwrap(), [17, 16, 88]);
+        assert_eq!(parse_combo(" alt + ctrl + F1 ").unwrap(), [17, 18, 112]);
+        assert_eq!(parse_combo("").unwrap(), Vec::<i64>::new());
+        assert!(parse_combo("Ctrl+Hyper").is_err());
+        assert!(parse_combo("Ctrl+").is_err());
+    }
+
+    #[test]
+    fn combos_format_with_modifiers_first() {
+        assert_eq!(format_combo(&[88, 16, 17]), "Ctrl+Shift+X");
+        assert_eq!(format_combo(&[18, 96]), "Alt+Num0");
+        assert_eq!(format_combo(&[]), "");
+        for combo in ["Ctrl+Alt+Delete", "Shift+F10", "`", "VK_255"] {
+            assert_eq!(format_combo(&parse_combo(combo).unwrap()), combo);
+        }
+    }
+
+    #[test]
+    fn conflicts_ignore_modifier_order() {
+        let shortcuts = bindings(&[
+            ("CmdDronesEngage", &[17, 16, 88]),
+            ("CmdReloadAmmo", &[16, 17, 88]),
+            ("CmdStopShip", &[17, 32]),
+            ("CmdToggleMap", &[]),
+            ("CmdToggleMarket", &[]),
+        ]);
+
+        let conflicts = find_conflicts(&shortcuts);
+        assert_eq!(conflicts.len(), 1);
+        assert_eq!(conflicts[0].keys, "Ctrl+Shift+X");
+        assert_eq!(conflicts[0].commands, ["CmdDronesEngage", "CmdReloadAmmo"]);
+    }
+
+    #[test]
+    fn reordered_modifiers_are_not_rewritten() {
+        let stored = |codes: &[i64]| Value::Tuple(codes.iter().map(|c| Value::Int(*c)).collect());
+        let mut root = Value::Dict(vec![(
+            Value::Str(SHORTCUT_GROUP.to_string()),
+            Value::Dict(vec![
+                (
+                    Value::Str("CmdReloadAmmo".to_string()),
+                    stored(&[16, 17, 88]),
+                ),
+                (Value::Str("CmdStopShip".to_string()), stored(&[17, 32])),
+            ]),
+        )]);
+        let imported = bindings(&[
+            ("CmdReloadAmmo", &parse_combo("Ctrl+Shift+X").unwrap()),
+            ("CmdStopShip", &parse_combo("Ctrl+S").unwrap()),
+        ]);
+
+        assert_eq!(apply_shortcuts(&mut root, &imported).unwrap(), 1);
+        let shortcuts = read_shortcuts(&root);
+        assert_eq!(shortcuts["CmdReloadAmmo"], [16, 17, 88]);
+        assert_eq!(shortcuts["CmdStopShip"], [17, 83]);
+    }
+}
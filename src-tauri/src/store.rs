@@ -0,0 +1,277 @@
+use crate::error::{AppError, AppResult, IoResultExt};
+use crate::evesettings::{app_data_dir, emit_data_changed, scan_backups, sha256_of_bytes};
+use crate::operations::{self, OperationRecorder};
+use crate::verify::QUARANTINE_DIR;
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::fs;
+use std::io::Read;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime};
+
+// Backups are stored once per distinct content under <server>/backup-store/ab/abcd...,
+// and each .bak file in a profile's backups folder is a small JSON reference to its
+// blob. Old full-copy .bak files keep working everywhere until they are migrated.
+const STORE_DIR: &str = "backup-store";
+// Blobs younger than this are never collected: a backup may have written its blob but
+// not yet its reference.
+const GC_GRACE: Duration = Duration::from_secs(300);
+// A reference is a hash and a size; anything larger is not one.
+const MAX_REF_SIZE: u64 = 1024;
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+struct BackupRef {
+    sha256: String,
+    size: u64,
+}
+
+#[derive(Serialize, Debug, Clone, Default)]
+pub struct StoreMigrationResult {
+    pub migrated_count: usize,
+    pub already_migrated_count: usize,
+    pub blobs_written: usize,
+    pub bytes_saved: u64,
+    pub blobs_removed: usize,
+    pub errors: Vec<String>,
+}
+
+/// Store directory shared by every profile of the server a backup belongs to.
+fn store_dir(backup_path: &Path) -> AppResult<PathBuf> {
+    backup_path
+        .parent() // backups
+        .and_then(|p| p.parent()) // settings_<profile>
+        .and_then(|p| p.parent()) // server folder
+        .map(|server| server.join(STORE_DIR))
+        .ok_or_else(|| {
+            AppError::invalid_input(format!(
+                "Invalid backup location: {}",
+                backup_path.display()
+            ))
+            .with_path(backup_path)
+        })
+}
+
+fn blob_path(store: &Path, sha256: &str) -> PathBuf {
+    store.join(&sha256[..2]).join(sha256)
+}
+
+fn read_ref(path: &Path) -> Option<BackupRef> {
+    if path.extension().and_then(|e| e.to_str()) != Some("bak") {
+        return None;
+    }
+    let mut file = fs::File::open(path).ok()?;
+    // Marshal streams start with 0x7E, so a leading brace can only be a reference and
+    // full-copy backups are never read past their first byte
+    let mut first = [0u8; 1];
+    file.read_exact(&mut first).ok()?;
+    if first[0] != b'{' || file.metadata().ok()?.len() > MAX_REF_SIZE {
+        return None;
+    }
+    let mut data = first.to_vec();
+    file.read_to_end(&mut data).ok()?;
+    serde_json::from_slice(&data).ok()
+}
+
+/// Writes `data` into the store unless an identical blob is already there. Returns the
+/// content hash and whether a new blob was written.
+fn put_blob(store: &Path, data: &[u8]) -> AppResult<(String, bool)> {
+    let sha256 = sha256_of_bytes(data);
+    let dest = blob_path(store, &sha256);
+    if dest.exists() {
+        return Ok((sha256, false));
+    }
+
+    let parent = dest
+        .parent()
+        .ok_or_else(|| AppError::invalid_input("Invalid store path").with_path(&dest))?;
+    fs::create_dir_all(parent).at("create", parent)?;
+    let tmp = dest.with_extension("tmp");
+    fs::write(&tmp, data).at("write", &tmp)?;
+    fs::rename(&tmp, &dest).map_err(|e| {
+        let _ = fs::remove_file(&tmp);
+        AppError::io("store", &dest, e)
+    })?;
+    Ok((sha256, true))
+}
+
+fn write_ref(backup_path: &Path, backup_ref: &BackupRef) -> AppResult<()> {
+    let content = serde_json::to_vec(backup_ref).map_err(|e| AppError::other(e.to_string()))?;
+    let tmp = backup_path.with_extension("bak.tmp");
+    fs::write(&tmp, content).at("write", &tmp)?;
+    fs::rename(&tmp, backup_path).map_err(|e| {
+        let _ = fs::remove_file(&tmp);
+        AppError::io("replace", backup_path, e)
+    })
+}
+
+/// Stores `data` and writes `backup_path` as a reference to it.
+pub(crate) fn store_backup(backup_path: &Path, data: &[u8]) -> AppResult<()> {
+    let store = store_dir(backup_path)?;
+    let (sha256, _) = put_blob(&store, data)?;
+    write_ref(
+        backup_path,
+        &BackupRef {
+            sha256,
+            size: data.len() as u64,
+        },
+    )
+}
+
+/// Path holding the actual bytes of `path`: the blob for a backup reference, the file
+/// itself otherwise.
+pub(crate) fn content_path(path: &Path) -> AppResult<PathBuf> {
+    match read_ref(path) {
+        Some(backup_ref) => Ok(blob_path(&store_dir(path)?, &backup_ref.sha256)),
+        None => Ok(path.to_path_buf()),
+    }
+}
+
+/// Content with the given hash from the store `backup_path` belongs to. Works after the
+/// backup itself is gone, for as long as the blob is kept.
+pub(crate) fn blob_contents(backup_path: &Path, sha256: &str) -> AppResult<Vec<u8>> {
+    let path = blob_path(&store_dir(backup_path)?, sha256);
+    fs::read(&path).at("read", &path)
+}
+
+pub(crate) fn read_contents(path: &Path) -> AppResult<Vec<u8>> {
+    let real = content_path(path)?;
+    fs::read(&real).at("read", path)
+}
+
+/// Hash recorded in a backup reference, without reading the blob.
+pub(crate) fn recorded_sha256(path: &Path) -> Option<String> {
+    read_ref(path).map(|backup_ref| backup_ref.sha256)
+}
+
+pub(crate) fn content_size(path: &Path) -> u64 {
+    match read_ref(path) {
+        Some(backup_ref) => backup_ref.size,
+        None => fs::metadata(path).map_or(0, |m| m.len()),
+    }
+}
+
+fn collect_refs(paths: impl IntoIterator<Item = PathBuf>, referenced: &mut HashSet<String>) {
+    for path in paths {
+        if let Some(backup_ref) = read_ref(&path) {
+            referenced.insert(backup_ref.sha256);
+        }
+    }
+}
+
+fn dir_files(dir: &Path) -> impl Iterator<Item = PathBuf> {
+    fs::read_dir(dir)
+        .into_iter()
+        .flatten()
+        .flatten()
+        .map(|entry| entry.path())
+}
+
+/// Removes blobs nothing references any more: no .bak file of the server, quarantined
+/// or not, and no snapshot or record in the operation journal under `data_dir`. Returns the
+/// number of blobs removed.
+pub(crate) fn collect_garbage(store: &Path, data_dir: &Path) -> AppResult<usize> {
+    let Some(server_dir) = store.parent() else {
+        return Ok(0);
+    };
+    if !store.is_dir() {
+        return Ok(0);
+    }
+
+    let mut referenced = HashSet::new();
+    for profile in fs::read_dir(server_dir).at("read", server_dir)?.flatten() {
+        let backups = profile.path().join("backups");
+        collect_refs(dir_files(&backups), &mut referenced);
+        collect_refs(dir_files(&backups.join(QUARANTINE_DIR)), &mut referenced);
+    }
+    // Snapshots taken before they held the content are references too
+    collect_refs(operations::snapshot_files(data_dir), &mut referenced);
+    referenced.extend(operations::pinned_blobs(data_dir));
+
+    let mut removed = 0;
+    for shard in fs::read_dir(store).at("read", store)?.flatten() {
+        let Ok(blobs) = fs::read_dir(shard.path()) else {
+            continue;
+        };
+        for blob in blobs.flatten() {
+            let name = blob.file_name().to_string_lossy().into_owned();
+            let recent = blob
+                .metadata()
+                .and_then(|m| m.modified())
+                .ok()
+                .and_then(|t| SystemTime::now().duration_since(t).ok())
+                .is_none_or(|age| age < GC_GRACE);
+            if referenced.contains(&name) || recent {
+                continue;
+            }
+            if fs::remove_file(blob.path()).is_ok() {
+                removed += 1;
+            }
+        }
+    }
+    Ok(removed)
+}
+
+/// Store directories of every server that has backups.
+pub(crate) fn store_dirs(backup_paths: &[&str]) -> HashSet<PathBuf> {
+    backup_paths
+        .iter()
+        .filter_map(|p| store_dir(Path::new(p)).ok())
+        .collect()
+}
+
+/// Converts full-copy .bak files into references to the content store.
+#[tauri::command]
+pub fn migrate_backup_store(
+    app: tauri::AppHandle,
+    custom_eve_path: Option<String>,
+) -> AppResult<StoreMigrationResult> {
+    let backups = scan_backups(custom_eve_path.as_deref())?;
+    let data_dir = app_data_dir(&app)?;
+    let mut recorder = OperationRecorder::begin(
+        &data_dir,
+        "migrate_backup_store",
+        serde_json::json!({ "custom_eve_path": custom_eve_path }),
+    )?;
+    let mut result = StoreMigrationResult::default();
+
+    for backup in &backups {
+        let path = Path::new(&backup.path);
+        if read_ref(path).is_some() {
+            result.already_migrated_count += 1;
+            continue;
+        }
+
+        let migrated = recorder.snapshot(path).and_then(|()| {
+            let data = fs::read(path).at("read", path)?;
+            let store = store_dir(path)?;
+            let (sha256, written) = put_blob(&store, &data)?;
+            let size = data.len() as u64;
+            write_ref(path, &BackupRef { sha256, size })?;
+            Ok((size, written))
+        });
+
+        match migrated {
+            Ok((size, written)) => {
+                result.migrated_count += 1;
+                if written {
+                    result.blobs_written += 1;
+                } else {
+                    result.bytes_saved += size;
+                }
+            }
+            Err(e) => result.errors.push(e.to_string()),
+        }
+    }
+
+    drop(recorder);
+
+    let paths: Vec<&str> = backups.iter().map(|b| b.path.as_str()).collect();
+    for store in store_dirs(&paths) {
+        result.blobs_removed += collect_garbage(&store, &data_dir)?;
+    }
+
+    if result.migrated_count > 0 {
+        emit_data_changed(&app);
+    }
+    Ok(result)
+}
@@ -1,3 +1,4 @@
+use crate::error::{AppError, AppResult, ErrorCode};
 use crate::esi::CLIENT;
 use serde::Serialize;
 
@@ -12,7 +13,7 @@ pub struct UpdateInfo {
 }
 
 #[tauri::command]
-pub async fn check_for_update() -> Result<Option<UpdateInfo>, String> {
+pub async fn check_for_update() -> AppResult<Option<UpdateInfo>> {
     let current_version = env!("CARGO_PKG_VERSION");
 
     let url = format!(
@@ -20,11 +21,12 @@ pub async fn check_for_update() -> Result<Option<UpdateInfo>, String> {
         GITHUB_REPO
     );
 
-    let response = CLIENT
-        .get(&url)
-        .send()
-        .await
-        .map_err(|e| format!("Failed to check for updates: {}", e))?;
+    let response = CLIENT.get(&url).send().await.map_err(|e| {
+        AppError::new(
+            ErrorCode::Network,
+            format!("Failed to check for updates: {}", e),
+        )
+    })?;
 
     if !response.status().is_success() {
         return Ok(None);
@@ -33,7 +35,7 @@ pub async fn check_for_update() -> Result<Option<UpdateInfo>, String> {
     let release: serde_json::Value = response
         .json()
         .await
-        .map_err(|e| format!("Failed to parse release: {}", e))?;
+        .map_err(|e| AppError::invalid_data(format!("Failed to parse release: {}", e)))?;
 
     let latest_version = release["tag_name"]
         .as_str()
@@ -0,0 +1,237 @@
+use crate::error::{AppError, AppResult, IoResultExt};
+use crate::evesettings::{
+    app_data_dir, emit_data_changed, parse_backup_filename, scan_backups, scan_installations,
+    sha256_of_bytes, BackupEntry, Server, SettingsKind,
+};
+use crate::marshal;
+use crate::metadata;
+use crate::operations::OperationRecorder;
+use crate::store;
+use serde::Serialize;
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::path::Path;
+
+pub(crate) const QUARANTINE_DIR: &str = "quarantine";
+
+#[derive(Serialize, Debug, Clone, Copy, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum BackupProblem {
+    /// The backup or the stored content it refers to cannot be read.
+    Unreadable,
+    /// The contents no longer match the hash recorded when the backup was made.
+    HashMismatch,
+    /// The contents are not a valid settings file.
+    Undecodable,
+    /// The sidecar exists but cannot be parsed.
+    InvalidMetadata,
+    /// The filename names a different kind or id than the metadata.
+    FilenameMismatch,
+    /// No settings file for the backed-up account or character exists any more.
+    Orphaned,
+    /// Same contents as an older backup of the same settings file.
+    Duplicate,
+}
+
+impl BackupProblem {
+    /// Problems that make the backup unusable, as opposed to merely redundant.
+    fn is_corruption(self) -> bool {
+        !matches!(self, BackupProblem::Orphaned | BackupProblem::Duplicate)
+    }
+}
+
+#[derive(Serialize, Debug, Clone)]
+pub struct BackupIssue {
+    pub backup: BackupEntry,
+    pub problem: BackupProblem,
+    pub detail: String,
+}
+
+#[derive(Serialize, Debug, Clone)]
+pub struct VerifyReport {
+    pub checked_count: usize,
+    pub ok_count: usize,
+    pub issues: Vec<BackupIssue>,
+    /// Backups moved into quarantine, at their new paths.
+    pub quarantined: Vec<String>,
+    pub errors: Vec<String>,
+}
+
+/// Checks one backup's contents. Returns the first problem found and, for intact
+/// backups, the content hash.
+fn check_backup(backup: &BackupEntry) -> Result<String, (BackupProblem, String)> {
+    let path = Path::new(&backup.path);
+    let filename = path.file_name().and_then(|n| n.to_str()).unwrap_or("");
+
+    let recorded = match metadata::read_metadata(path) {
+        Some(metadata) => {
+            let named = parse_backup_filename(filename).map(|(_, kind, id, _)| (kind, id));
+            if named != Some((metadata.kind, metadata.original_id.clone())) {
+                return Err((
+                    BackupProblem::FilenameMismatch,
+                    format!(
+                        "Metadata describes {} {}",
+                        metadata.kind.file_tag(),
+                        metadata.original_id
+                    ),
+                ));
+            }
+            metadata.sha256
+        }
+        None if metadata::sidecar_path(path).exists() => {
+            return Err((
+                BackupProblem::InvalidMetadata,
+                format!("{} is not valid", metadata::sidecar_path(path).display()),
+            ));
+        }
+        None => None,
+    };
+
+    let data =
+        store::read_contents(path).map_err(|e| (BackupProblem::Unreadable, e.to_string()))?;
+    let actual = sha256_of_bytes(&data);
+
+    if let Some(expected) = recorded.or_else(|| store::recorded_sha256(path)) {
+        if expected != actual {
+            return Err((
+                BackupProblem::HashMismatch,
+                format!("Expected sha256 {}, found {}", expected, actual),
+            ));
+        }
+    }
+
+    marshal::decode(&data).map_err(|e| (BackupProblem::Undecodable, e.to_string()))?;
+    Ok(actual)
+}
+
+/// Moves a backup and its sidecar into a quarantine folder next to it, where scans no
+/// longer pick it up. A backup that points into the content store is quarantined with
+/// a copy of its content, so the file stays inspectable whatever happens to the store.
+/// Returns the new path.
+fn quarantine_backup(path: &Path, recorder: &mut OperationRecorder) -> AppResult<String> {
+    let invalid = || AppError::invalid_input("Invalid backup path").with_path(path);
+    let dir = path.parent().ok_or_else(invalid)?.join(QUARANTINE_DIR);
+    fs::create_dir_all(&dir).at("create", &dir)?;
+
+    let sidecar = metadata::sidecar_path(path);
+    for file in [sidecar.as_path(), path] {
+        if !file.exists() {
+            continue;
+        }
+        let dest = dir.join(file.file_name().ok_or_else(invalid)?);
+        recorder.snapshot(file)?;
+        let content = store::content_path(file)?;
+        if content != file && content.exists() {
+            fs::copy(&content, &dest).at("quarantine", file)?;
+            fs::remove_file(file).at("delete", file)?;
+        } else {
+            fs::rename(file, &dest).at("quarantine", file)?;
+        }
+    }
+
+    Ok(dir
+        .join(path.file_name().ok_or_else(invalid)?)
+        .to_string_lossy()
+        .into_owned())
+}
+
+/// Checks every backup for corruption, backups of accounts and characters that no longer
+/// exist, and redundant copies. With `quarantine`, corrupted backups are moved aside;
+/// orphaned and duplicate backups are only reported.
+#[tauri::command]
+pub fn verify_backups(
+    app: tauri::AppHandle,
+    custom_eve_path: Option<String>,
+    quarantine: bool,
+) -> AppResult<VerifyReport> {
+    let mut backups = scan_backups(custom_eve_path.as_deref())?;
+    // Oldest first, so the first copy of some content is the one duplicates point at
+    backups.reverse();
+
+    let (server_profiles, _) = scan_installations(custom_eve_path.as_deref())?;
+    let live: HashSet<(Server, SettingsKind, &str)> = server_profiles
+        .iter()
+        .flat_map(|(server, profiles)| {
+            profiles
+                .iter()
+                .flat_map(|p| p.accounts.iter().chain(p.characters.iter()))
+                .map(move |entry| (*server, entry.kind, entry.id.as_str()))
+        })
+        .collect();
+
+    let mut issues = Vec::new();
+    let mut first_copy: HashMap<(SettingsKind, String, String), String> = HashMap::new();
+
+    for backup in &backups {
+        let sha256 = match check_backup(backup) {
+            Ok(sha256) => sha256,
+            Err((problem, detail)) => {
+                issues.push(BackupIssue {
+                    backup: backup.clone(),
+                    problem,
+                    detail,
+                });
+                continue;
+            }
+        };
+
+        let exists = live.iter().any(|(server, kind, id)| {
+            backup.server.is_none_or(|s| s == *server)
+                && *kind == backup.kind
+                && *id == backup.original_id
+        });
+        if !exists {
+            issues.push(BackupIssue {
+                backup: backup.clone(),
+                problem: BackupProblem::Orphaned,
+                detail: format!(
+                    "No {} settings with id {}",
+                    backup.kind.file_tag(),
+                    backup.original_id
+                ),
+            });
+        }
+
+        let key = (backup.kind, backup.original_id.clone(), sha256);
+        match first_copy.get(&key) {
+            Some(original) => issues.push(BackupIssue {
+                backup: backup.clone(),
+                problem: BackupProblem::Duplicate,
+                detail: format!("Same contents as {}", original),
+            }),
+            None => {
+                first_copy.insert(key, backup.path.clone());
+            }
+        }
+    }
+
+    let flagged: HashSet<&str> = issues.iter().map(|i| i.backup.path.as_str()).collect();
+    let ok_count = backups.len() - flagged.len();
+
+    let mut quarantined = Vec::new();
+    let mut errors = Vec::new();
+    if quarantine {
+        let mut recorder = OperationRecorder::begin(
+            &app_data_dir(&app)?,
+            "verify_backups",
+            serde_json::json!({ "custom_eve_path": custom_eve_path, "quarantine": quarantine }),
+        )?;
+        for issue in issues.iter().filter(|i| i.problem.is_corruption()) {
+            match quarantine_backup(Path::new(&issue.backup.path), &mut recorder) {
+                Ok(path) => quarantined.push(path),
+                Err(e) => errors.push(e.to_string()),
+            }
+        }
+        if !quarantined.is_empty() {
+            emit_data_changed(&app);
+        }
+    }
+
+    Ok(VerifyReport {
+        checked_count: backups.len(),
+        ok_count,
+        issues,
+        quarantined,
+        errors,
+    })
+}
@@ -0,0 +1,215 @@
+use crate::error::{AppError, AppResult, ErrorCode};
+use crate::evesettings::{
+    app_data_dir, backup_entry, backup_metadata, eve_settings_root, load_aliases, scan_backups,
+    scan_installations, settings_entry_at, BackupEntry, SettingsEntry,
+};
+use crate::location;
+use crate::metadata;
+use notify_debouncer_mini::notify::{RecommendedWatcher, RecursiveMode};
+use notify_debouncer_mini::{new_debouncer, DebounceEventResult, Debouncer};
+use serde::Serialize;
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::Duration;
+use tauri::{Emitter, Manager};
+
+/// Quiet period before a burst of writes is reported. The client rewrites several files
+/// on logout, and a copy touches each target in turn.
+const DEBOUNCE: Duration = Duration::from_millis(500);
+
+#[derive(Serialize, Debug, Clone, Copy, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum Change {
+    Added,
+    Modified,
+    Removed,
+}
+
+/// Payload of the `settings-entry-changed` event. `entry` is `None` for removals.
+#[derive(Serialize, Debug, Clone)]
+pub struct SettingsEntryEvent {
+    pub change: Change,
+    pub path: String,
+    pub entry: Option<SettingsEntry>,
+}
+
+/// Payload of the `backup-changed` event. `backup` is `None` for removals.
+#[derive(Serialize, Debug, Clone)]
+pub struct BackupEvent {
+    pub change: Change,
+    pub path: String,
+    pub backup: Option<BackupEntry>,
+}
+
+/// Watches the EVE settings tree and the backup folder, turning file changes into
+/// per-entry events so the UI can update in place instead of rescanning.
+#[derive(Default)]
+pub struct SettingsWatcher {
+    debouncer: Mutex<Option<Debouncer<RecommendedWatcher>>>,
+    /// Settings files and backups that exist as far as the last event is concerned,
+    /// used to tell additions from modifications.
+    known: Mutex<HashSet<PathBuf>>,
+}
+
+fn is_settings_file(path: &Path) -> bool {
+    path.file_name()
+        .and_then(|n| n.to_str())
+        .is_some_and(|n| n.starts_with("core_") && n.ends_with(".dat"))
+}
+
+// Backups sit directly in a `backups` folder; anything deeper, like quarantine, is not
+// shown in the app
+fn is_backup_file(path: &Path) -> bool {
+    path.extension().and_then(|e| e.to_str()) == Some("bak")
+        && path
+            .parent()
+            .and_then(|dir| dir.file_name())
+            .is_some_and(|name| name == "backups")
+}
+
+// A sidecar change means the backup's notes, tags or name changed
+fn backup_for_sidecar(path: &Path) -> Option<PathBuf> {
+    if path.extension().and_then(|e| e.to_str()) != Some("json") {
+        return None;
+    }
+    let backup = path.with_extension("bak");
+    (is_backup_file(&backup) && metadata::sidecar_path(&backup) == path).then_some(backup)
+}
+
+fn change_for(known: &mut HashSet<PathBuf>, path: &Path) -> Option<Change> {
+    match (path.is_file(), known.contains(path)) {
+        (true, true) => Some(Change::Modified),
+        (true, false) => {
+            known.insert(path.to_path_buf());
+            Some(Change::Added)
+        }
+        (false, true) => {
+            known.remove(path);
+            Some(Change::Removed)
+        }
+        // Temporary files that came and went within the debounce window
+        (false, false) => None,
+    }
+}
+
+fn handle_events(app: &tauri::AppHandle, paths: Vec<PathBuf>) {
+    let watcher = app.state::<SettingsWatcher>();
+    let aliases = app_data_dir(app)
+        .map(|dir| load_aliases(&dir))
+        .unwrap_or_default();
+
+    let mut seen = HashSet::new();
+    for path in paths {
+        let path = backup_for_sidecar(&path).unwrap_or(path);
+        if !seen.insert(path.clone()) {
+            continue;
+        }
+
+        if is_settings_file(&path) {
+            let Some(change) = change_for(&mut watcher.known.lock().unwrap(), &path) else {
+                continue;
+            };
+            let entry = match change {
+                Change::Removed => None,
+                _ => settings_entry_at(&path, &aliases),
+            };
+            // Settings files outside a profile folder are not shown in the app
+            if change != Change::Removed && entry.is_none() {
+                continue;
+            }
+            let _ = app.emit(
+                "settings-entry-changed",
+                SettingsEntryEvent {
+                    change,
+                    path: path.to_string_lossy().into_owned(),
+                    entry,
+                },
+            );
+        } else if is_backup_file(&path) {
+            let Some(change) = change_for(&mut watcher.known.lock().unwrap(), &path) else {
+                continue;
+            };
+            let backup = match change {
+                Change::Removed => None,
+                _ => backup_metadata(&path).map(|metadata| backup_entry(&path, metadata)),
+            };
+            if change != Change::Removed && backup.is_none() {
+                continue;
+            }
+            let _ = app.emit(
+                "backup-changed",
+                BackupEvent {
+                    change,
+                    path: path.to_string_lossy().into_owned(),
+                    backup,
+                },
+            );
+        }
+    }
+}
+
+/// Registers the watcher state. Watching starts with `watch_settings`.
+pub fn init(app: &tauri::AppHandle) {
+    app.manage(SettingsWatcher::default());
+}
+
+/// Starts watching the EVE settings folder (or `custom_eve_path`) and the backup
+/// folder, replacing any earlier watch. Emits `settings-entry-changed` and
+/// `backup-changed` as files change, after a short debounce.
+#[tauri::command]
+pub fn watch_settings(
+    app: tauri::AppHandle,
+    watcher: tauri::State<'_, SettingsWatcher>,
+    custom_eve_path: Option<String>,
+) -> AppResult<()> {
+    let root = eve_settings_root(custom_eve_path.as_deref())
+        .filter(|root| root.is_dir())
+        .ok_or_else(|| AppError::not_found("EVE settings directory not found"))?;
+
+    // Stop the previous watch before taking stock, so nothing slips in between
+    *watcher.debouncer.lock().unwrap() = None;
+
+    let mut known = HashSet::new();
+    if let Ok((server_profiles, _)) = scan_installations(custom_eve_path.as_deref()) {
+        for profile in server_profiles.values().flatten() {
+            for entry in profile.accounts.iter().chain(profile.characters.iter()) {
+                known.insert(PathBuf::from(&entry.path));
+            }
+        }
+    }
+    for backup in scan_backups(custom_eve_path.as_deref())? {
+        known.insert(PathBuf::from(backup.path));
+    }
+    *watcher.known.lock().unwrap() = known;
+
+    let handler_app = app.clone();
+    let mut debouncer = new_debouncer(DEBOUNCE, move |result: DebounceEventResult| {
+        if let Ok(events) = result {
+            handle_events(&handler_app, events.into_iter().map(|e| e.path).collect());
+        }
+    })
+    .map_err(|e| AppError::new(ErrorCode::Io, format!("Failed to start watcher: {}", e)))?;
+
+    let mut dirs = vec![root];
+    if let Some(backup_root) = location::backup_root().filter(|r| r.is_dir()) {
+        if !backup_root.starts_with(&dirs[0]) {
+            dirs.push(backup_root);
+        }
+    }
+    for dir in &dirs {
+        debouncer
+            .watcher()
+            .watch(dir, RecursiveMode::Recursive)
+            .map_err(|e| {
+                AppError::new(
+                    ErrorCode::Io,
+                    format!("Failed to watch {}: {}", dir.display(), e),
+                )
+                .with_path(dir)
+            })?;
+    }
+
+    *watcher.debouncer.lock().unwrap() = Some(debouncer);
+    Ok(())
+}
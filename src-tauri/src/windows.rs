@@ -0,0 +1,366 @@
+use crate::clients;
+use crate::error::{AppError, AppResult};
+use crate::evesettings::{
+    app_data_dir, emit_data_changed, read_editable_settings, read_settings_document,
+    read_source_document, write_settings_document, TargetResult,
+};
+use crate::marshal::Value;
+use crate::operations::OperationRecorder;
+use crate::sections;
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+
+const WINDOWS_GROUP: &str = "windows";
+const GEOMETRY_PREFIX: &str = "windowSizesAndPositions";
+
+/// A monitor as the client sees it: physical resolution plus the in-game UI scale.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+pub struct ScreenSpec {
+    pub width: u32,
+    pub height: u32,
+    pub ui_scale: f64,
+}
+
+impl ScreenSpec {
+    // Window geometry is stored in UI points, i.e. physical pixels divided by the UI scale
+    fn desktop(&self) -> AppResult<(f64, f64)> {
+        if self.width == 0 || self.height == 0 || self.ui_scale <= 0.0 {
+            return Err(AppError::invalid_input(format!(
+                "Invalid screen {}x{} at {}x UI scale",
+                self.width, self.height, self.ui_scale
+            )));
+        }
+        Ok((
+            self.width as f64 / self.ui_scale,
+            self.height as f64 / self.ui_scale,
+        ))
+    }
+}
+
+#[derive(Serialize, Debug, Clone)]
+pub struct WindowGeometry {
+    pub key: String,
+    pub window: String,
+    pub x: i64,
+    pub y: i64,
+    pub width: i64,
+    pub height: i64,
+}
+
+#[derive(Serialize, Debug, Clone)]
+pub struct WindowLayout {
+    pub path: String,
+    /// Desktop size (in UI points) the layout was saved at, when the file records it.
+    pub desktop_width: Option<i64>,
+    pub desktop_height: Option<i64>,
+    pub windows: Vec<WindowGeometry>,
+}
+
+// Geometry tuples are (left, top, width, height[, desktop width, desktop height])
+fn geometry(value: &Value) -> Option<Vec<i64>> {
+    match value.resolve() {
+        Value::Tuple(items) | Value::List(items) if items.len() >= 4 => {
+            items.iter().map(Value::as_int).collect()
+        }
+        _ => None,
+    }
+}
+
+fn key_label(key: &Value) -> String {
+    match key.resolve() {
+        Value::Int(i) => i.to_string(),
+        other => other.as_str().map_or_else(|| "?".into(), str::to_string),
+    }
+}
+
+fn transform(geom: &[i64], from: (f64, f64), to: (f64, f64)) -> Vec<i64> {
+    let (fx, fy) = (to.0 / from.0, to.1 / from.1);
+    let (max_w, max_h) = ((to.0.floor() as i64).max(1), (to.1.floor() as i64).max(1));
+
+    let width = ((geom[2] as f64 * fx).round() as i64).clamp(1, max_w);
+    let height = ((geom[3] as f64 * fy).round() as i64).clamp(1, max_h);
+    let x = ((geom[0] as f64 * fx).round() as i64).clamp(0, max_w - width);
+    let y = ((geom[1] as f64 * fy).round() as i64).clamp(0, max_h - height);
+
+    let mut out = vec![x, y, width, height];
+    if geom.len() >= 6 {
+        out.extend([max_w, max_h]);
+        out.extend_from_slice(&geom[6..]);
+    } else {
+        out.extend_from_slice(&geom[4..]);
+    }
+    out
+}
+
+/// Rescales every stored window rectangle to the `to` desktop and clamps it on-screen.
+/// `from` is used for entries that do not record the desktop size they were saved at,
+/// and overrides the recorded size when given. Returns the number of windows changed.
+fn transform_layout(
+    root: &mut Value,
+    from: Option<(f64, f64)>,
+    to: (f64, f64),
+) -> AppResult<usize> {
+    let Some(groups) = sections::groups_mut(root) else {
+        return Ok(0);
+    };
+    let Some(windows) = groups
+        .iter_mut()
+        .find(|(k, _)| k.as_str() == Some(WINDOWS_GROUP))
+        .and_then(|(_, v)| v.as_dict_mut())
+    else {
+        return Ok(0);
+    };
+
+    let mut changed = 0;
+
+    for (key, value) in windows.iter_mut() {
+        if !key.as_str().is_some_and(|k| k.starts_with(GEOMETRY_PREFIX)) {
+            continue;
+        }
+        let Some(entries) = value.as_dict_mut() else {
+            continue;
+        };
+
+        for (_, rect) in entries.iter_mut() {
+            let Some(geom) = geometry(rect) else {
+                continue;
+            };
+            let saved = (geom.len() >= 6 && geom[4] > 0 && geom[5] > 0)
+                .then(|| (geom[4] as f64, geom[5] as f64));
+            let Some(from) = from.or(saved) else {
+                continue;
+            };
+
+            let updated = transform(&geom, from, to);
+            if updated == geom {
+                continue;
+            }
+            if let Value::Tuple(items) | Value::List(items) = rect.resolve_mut() {
+                for (item, n) in items.iter_mut().zip(updated) {
+                    *item = Value::Int(n);
+                }
+            }
+            changed += 1;
+        }
+    }
+
+    Ok(changed)
+}
+
+fn apply_layout_into(
+    source: &Value,
+    target: &Path,
+    from: Option<(f64, f64)>,
+    to: (f64, f64),
+    recorder: &mut OperationRecorder,
+) -> AppResult<()> {
+    let section = sections::find_section(WINDOWS_GROUP)
+        .ok_or_else(|| AppError::other("Unknown settings section"))?;
+    let mut doc = read_editable_settings(target)?;
+
+    let copied =
+        sections::copy_section(source, &mut doc.root, section).map_err(|e| e.with_path(target))?;
+    let transformed = transform_layout(&mut doc.root, from, to)?;
+
+    if copied + transformed > 0 {
+        recorder.back_up(target, "pre_layout")?;
+        write_settings_document(target, &doc)?;
+    }
+    Ok(())
+}
+
+#[tauri::command]
+pub fn get_window_layout(path: String) -> AppResult<WindowLayout> {
+    let doc = read_settings_document(Path::new(&path))?;
+    let mut layout = WindowLayout {
+        path,
+        desktop_width: None,
+        desktop_height: None,
+        windows: Vec::new(),
+    };
+
+    let Some(windows) = sections::group_dict(&doc.root, WINDOWS_GROUP) else {
+        return Ok(layout);
+    };
+
+    for (key, value) in windows {
+        let Some(key) = key.as_str().filter(|k| k.starts_with(GEOMETRY_PREFIX)) else {
+            continue;
+        };
+        let Some(entries) = value.as_dict() else {
+            continue;
+        };
+
+        for (window, rect) in entries {
+            let Some(geom) = geometry(rect) else {
+                continue;
+            };
+            if geom.len() >= 6 && layout.desktop_width.is_none() {
+                layout.desktop_width = Some(geom[4]);
+                layout.desktop_height = Some(geom[5]);
+            }
+            layout.windows.push(WindowGeometry {
+                key: key.to_string(),
+                window: key_label(window),
+                x: geom[0],
+                y: geom[1],
+                width: geom[2],
+                height: geom[3],
+            });
+        }
+    }
+
+    Ok(layout)
+}
+
+#[tauri::command]
+pub fn apply_window_layout(
+    app: tauri::AppHandle,
+    source_path: String,
+    target_paths: Vec<String>,
+    source_screen: Option<ScreenSpec>,
+    target_screen: ScreenSpec,
+) -> AppResult<Vec<TargetResult>> {
+    let results = apply_layout_files(
+        &app_data_dir(&app)?,
+        source_path,
+        target_paths,
+        source_screen,
+        target_screen,
+    )?;
+    if results.iter().any(|r| r.error.is_none()) {
+        emit_data_changed(&app);
+    }
+    Ok(results)
+}
+
+pub(crate) fn apply_layout_files(
+    data_dir: &Path,
+    source_path: String,
+    target_paths: Vec<String>,
+    source_screen: Option<ScreenSpec>,
+    target_screen: ScreenSpec,
+) -> AppResult<Vec<TargetResult>> {
+    let from = source_screen.map(|s| s.desktop()).transpose()?;
+    let to = target_screen.desktop()?;
+    let source = read_source_document(Path::new(&source_path))?;
+    clients::ensure_writable(&target_paths)?;
+
+    let mut recorder = OperationRecorder::begin(
+        data_dir,
+        "apply_window_layout",
+        serde_json::json!({
+            "source_path": source_path,
+            "target_paths": target_paths,
+            "source_screen": source_screen,
+            "target_screen": target_screen,
+        }),
+    )?;
+    let mut results = Vec::with_capacity(target_paths.len());
+
+    for target_path in target_paths {
+        let target = PathBuf::from(&target_path);
+        let error = apply_layout_into(&source.root, &target, from, to, &mut recorder).err();
+        results.push(TargetResult {
+            path: target_path,
+            error,
+        });
+    }
+
+    Ok(results)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const HD: (f64, f64) = (1920.0, 1080.0);
+
+    fn screen(width: u32, height: u32, ui_scale: f64) -> (f64, f64) {
+        ScreenSpec {
+            width,
+            height,
+            ui_scale,
+        }
+        .desktop()
+        .unwrap()
+    }
+
+    fn layout(rects: Vec<Vec<i64>>) -> Value {
+        let rects = rects
+            .into_iter()
+            .enumerate()
+            .map(|(i, rect)| {
+                let rect = rect.into_iter().map(Value::Int).collect();
+                (Value::Int(i as i64), Value::Tuple(rect))
+            })
+            .collect();
+        let windows = vec![(
+            Value::Str(format!("{}_0", GEOMETRY_PREFIX)),
+            Value::Dict(rects),
+        )];
+        Value::Dict(vec![(
+            Value::Str(WINDOWS_GROUP.to_string()),
+            Value::Dict(windows),
+        )])
+    }
+
+    fn rects(root: &Value) -> Vec<Vec<i64>> {
+        let windows = sections::group_dict(root, WINDOWS_GROUP).unwrap();
+        windows[0]
+            .1
+            .as_dict()
+            .unwrap()
+            .iter()
+            .map(|(_, rect)| geometry(rect).unwrap())
+            .collect()
+    }
+
+    #[test]
+    fn uhd_layout_scales_down_to_hd_at_125_percent() {
+        let from = screen(3840, 2160, 1.0);
+        let to = screen(1920, 1080, 1.25);
+        assert_eq!(to, (1536.0, 864.0));
+
+        let mut root = layout(vec![
+            vec![1920, 1080, 960, 540, 3840, 2160],
+            vec![0, 0, 3840, 2160, 3840, 2160],
+        ]);
+        assert_eq!(transform_layout(&mut root, Some(from), to).unwrap(), 2);
+        assert_eq!(
+            rects(&root),
+            [
+                vec![768, 432, 384, 216, 1536, 864],
+                vec![0, 0, 1536, 864, 1536, 864],
+            ]
+        );
+    }
+
+    #[test]
+    fn window_wider_than_the_screen_is_shrunk_to_fit() {
+        assert_eq!(transform(&[100, 50, 3000, 500], HD, HD), [0, 50, 1920, 500]);
+        assert_eq!(transform(&[10, 10, 0, -5], HD, HD), [10, 10, 1, 1]);
+    }
+
+    #[test]
+    fn off_screen_windows_are_moved_back_on() {
+        assert_eq!(transform(&[-200, -40, 400, 300], HD, HD), [0, 0, 400, 300]);
+        assert_eq!(
+            transform(&[5000, 2000, 400, 300], HD, HD),
+            [1520, 780, 400, 300]
+        );
+    }
+
+    #[test]
+    fn rects_without_a_desktop_size_need_an_explicit_source() {
+        let mut root = layout(vec![vec![960, 540, 480, 270], vec![0, 0, 100, 100, 0, 0]]);
+        let to = screen(1280, 720, 1.0);
+        assert_eq!(transform_layout(&mut root, None, to).unwrap(), 0);
+
+        assert_eq!(transform_layout(&mut root, Some(HD), to).unwrap(), 2);
+        assert_eq!(
+            rects(&root),
+            [vec![640, 360, 320, 180], vec![0, 0, 67, 67, 1280, 720]]
+        );
+    }
+}
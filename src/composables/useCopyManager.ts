@@ -14,8 +14,14 @@ import type {
     ExportResult,
     ImportAnalysis,
     ImportResultInfo,
+    RestoreResult,
+    PendingOperation,
+    QueuedOperationResult,
+    SettingsEntryEvent,
+    BackupEvent,
+    TargetResult,
 } from '@/types'
-import { isBackup } from '@/types'
+import { errorMessage, isAppError, isBackup } from '@/types'
 import { useConfirm } from './useConfirm'
 import { usePrompt } from './usePrompt'
 import { useI18n } from './useI18n'
@@ -39,6 +45,92 @@ async function setupListener(loadDataFn: () => Promise<void>) {
     })
 }
 
+let watchListenersSetup = false
+
+// Keeps appData in step with files changed outside the app, e.g. by the client on
+// logout, without a full rescan
+function applySettingsEntryEvent({ change, path, entry }: SettingsEntryEvent) {
+    if (!appData.value) return
+    for (const server of appData.value.servers) {
+        for (const profile of server.profiles) {
+            for (const list of [profile.accounts, profile.characters]) {
+                const index = list.findIndex((e) => e.path === path)
+                if (index === -1) continue
+                if (change === 'removed') {
+                    list.splice(index, 1)
+                } else if (entry) {
+                    list[index].modified_time = entry.modified_time
+                    list[index].relative_time = entry.relative_time
+                }
+                return
+            }
+        }
+    }
+
+    if (change === 'removed' || !entry) return
+    const profile = appData.value.servers
+        .find((s) => s.info.id === entry.server)
+        ?.profiles.find((p) => p.name === entry.profile)
+    if (!profile) return
+    const list = entry.kind === 'user' ? profile.accounts : profile.characters
+    list.push(entry)
+    list.sort((a, b) => a.id.localeCompare(b.id))
+}
+
+function applyBackupEvent({ change, path, backup }: BackupEvent) {
+    if (!appData.value) return
+    const backups = appData.value.backups
+    const index = backups.findIndex((b) => b.path === path)
+
+    if (change === 'removed') {
+        if (index !== -1) backups.splice(index, 1)
+        return
+    }
+    if (!backup) return
+    if (index !== -1) {
+        backups[index] = {
+            ...backup,
+            original_name: backup.original_name ?? backups[index].original_name,
+        }
+        return
+    }
+    backups.push(backup)
+    backups.sort((a, b) => b.timestamp - a.timestamp)
+}
+
+async function setupWatchListeners() {
+    if (watchListenersSetup) return
+    watchListenersSetup = true
+    await listen<SettingsEntryEvent>('settings-entry-changed', (event) => {
+        applySettingsEntryEvent(event.payload)
+        if (event.payload.change === 'removed') {
+            targets.value = targets.value.filter(
+                (t) => t.path !== event.payload.path
+            )
+        }
+    })
+    await listen<BackupEvent>('backup-changed', (event) => {
+        applyBackupEvent(event.payload)
+    })
+}
+
+let queueListenerSetup = false
+
+async function setupQueueListener(t: (key: string) => string) {
+    if (queueListenerSetup) return
+    queueListenerSetup = true
+    await listen<QueuedOperationResult>('queued-operation-finished', (event) => {
+        const { error } = event.payload
+        if (error) {
+            toast.error(t('toast.queuedOperationFailed'), {
+                description: error.message,
+            })
+        } else {
+            toast.success(t('toast.queuedOperationDone'))
+        }
+    })
+}
+
 export function useCopyManager() {
     const { confirm } = useConfirm()
     const { prompt } = usePrompt()
@@ -79,12 +171,23 @@ export function useCopyManager() {
                 }
             }
         } catch (e: unknown) {
-            toast.error(t('toast.loadDataFailed'), { description: String(e) })
+            toast.error(t('toast.loadDataFailed'), { description: errorMessage(e) })
         } finally {
             loading.value = false
         }
     }
 
+    async function watchSettings() {
+        try {
+            await setupWatchListeners()
+            await invoke('watch_settings', {
+                customEvePath: customEvePath.value,
+            })
+        } catch (e) {
+            console.error('Failed to watch settings folder:', e)
+        }
+    }
+
     async function loadCustomPath() {
         try {
             const store = await load('settings.json')
@@ -111,8 +214,9 @@ export function useCopyManager() {
                 description: selected,
             })
             await loadData()
+            await watchSettings()
         } catch (e: unknown) {
-            toast.error(t('toast.setPathFailed'), { description: String(e) })
+            toast.error(t('toast.setPathFailed'), { description: errorMessage(e) })
         }
     }
 
@@ -125,8 +229,9 @@ export function useCopyManager() {
                 description: t('toast.pathResetDesc'),
             })
             await loadData()
+            await watchSettings()
         } catch (e: unknown) {
-            toast.error(t('toast.resetPathFailed'), { description: String(e) })
+            toast.error(t('toast.resetPathFailed'), { description: errorMessage(e) })
         }
     }
 
@@ -134,6 +239,7 @@ export function useCopyManager() {
         await setupListener(loadData)
         await loadCustomPath()
         await loadData()
+        await watchSettings()
     }
 
     function setSource(item: SourceItem) {
@@ -200,6 +306,29 @@ export function useCopyManager() {
         }
     }
 
+    // Offers to run a write refused because EVE is running once the client exits.
+    // Returns whether the error was handled that way.
+    async function offerToQueue(
+        e: unknown,
+        operation: PendingOperation
+    ): Promise<boolean> {
+        if (!isAppError(e) || e.code !== 'client_running') return false
+
+        const queue = await confirm({
+            title: t('dialog.clientRunning'),
+            description: t('dialog.clientRunningDesc', { message: e.message }),
+            confirmText: t('dialog.runWhenClosed'),
+        })
+        if (!queue) return true
+
+        await setupQueueListener(t)
+        await invoke('queue_operation', { operation })
+        toast.info(t('toast.operationQueued'), {
+            description: t('toast.operationQueuedDesc'),
+        })
+        return true
+    }
+
     async function executeCopy() {
         if (!source.value || targets.value.length === 0) return
 
@@ -211,19 +340,43 @@ export function useCopyManager() {
         if (!confirmed) return
 
         copying.value = true
+        const sourcePath = source.value.path
+        const targetPaths = targets.value.map((t) => t.path)
         try {
-            const sourcePath = source.value.path
-            const targetPaths = targets.value.map((t) => t.path)
-            const count = await invoke<number>('copy_settings', {
+            const results = await invoke<TargetResult[]>('copy_settings', {
                 sourcePath,
                 targetPaths,
             })
-            toast.success(t('toast.settingsCopied'), {
-                description: t('toast.settingsCopiedDesc', { count }),
-            })
-            targets.value = []
+            const failed = results.filter((r) => r.error)
+            const count = results.length - failed.length
+            if (failed.length === 0) {
+                toast.success(t('toast.settingsCopied'), {
+                    description: t('toast.settingsCopiedDesc', { count }),
+                })
+            } else {
+                toast.error(t('toast.copyFailed'), {
+                    description: t('toast.copyPartialDesc', {
+                        count,
+                        total: results.length,
+                        error: errorMessage(failed[0].error),
+                    }),
+                })
+            }
+            // Failed targets stay selected so the copy can be retried
+            targets.value = targets.value.filter((target) =>
+                failed.some((r) => r.path === target.path)
+            )
         } catch (e: unknown) {
-            toast.error(t('toast.copyFailed'), { description: String(e) })
+            const queued = await offerToQueue(e, {
+                type: 'copy',
+                source_path: sourcePath,
+                target_paths: targetPaths,
+            })
+            if (queued) {
+                targets.value = []
+                return
+            }
+            toast.error(t('toast.copyFailed'), { description: errorMessage(e) })
         } finally {
             copying.value = false
         }
@@ -243,12 +396,13 @@ export function useCopyManager() {
             await invoke('create_backup', {
                 sourcePath: entry.path,
                 backupName: name,
+                characterName: entry.character?.name ?? null,
             })
             toast.success(t('toast.backupCreated'), {
                 description: t('toast.backupCreatedDesc', { name }),
             })
         } catch (e: unknown) {
-            toast.error(t('toast.backupFailed'), { description: String(e) })
+            toast.error(t('toast.backupFailed'), { description: errorMessage(e) })
         }
     }
 
@@ -274,7 +428,7 @@ export function useCopyManager() {
                 source.value = null
             }
         } catch (e: unknown) {
-            toast.error(t('toast.deleteFailed'), { description: String(e) })
+            toast.error(t('toast.deleteFailed'), { description: errorMessage(e) })
         }
     }
 
@@ -295,15 +449,15 @@ export function useCopyManager() {
         if (!confirmed) return
 
         try {
-            await invoke<number>('copy_settings', {
-                sourcePath: backup.path,
-                targetPaths: [entry.path],
+            await invoke<RestoreResult>('restore_backup', {
+                backupPath: backup.path,
+                targetPath: entry.path,
             })
             toast.success(t('toast.backupRestored'), {
                 description: t('toast.backupRestoredDesc', { name: backup.name }),
             })
         } catch (e: unknown) {
-            toast.error(t('toast.restoreFailed'), { description: String(e) })
+            toast.error(t('toast.restoreFailed'), { description: errorMessage(e) })
         }
     }
 
@@ -317,15 +471,15 @@ export function useCopyManager() {
         if (!confirmed) return
 
         try {
-            await invoke<number>('copy_settings', {
-                sourcePath: backup.path,
-                targetPaths: [target.path],
+            await invoke<RestoreResult>('restore_backup', {
+                backupPath: backup.path,
+                targetPath: target.path,
             })
             toast.success(t('toast.backupApplied'), {
                 description: t('toast.backupAppliedDesc', { backup: backup.name, target: target.display_name }),
             })
         } catch (e: unknown) {
-            toast.error(t('toast.applyFailed'), { description: String(e) })
+            toast.error(t('toast.applyFailed'), { description: errorMessage(e) })
         }
     }
 
@@ -365,7 +519,7 @@ export function useCopyManager() {
                 description: t('toast.settingsExportedDesc', { count: result.file_count, path: result.path }),
             })
         } catch (e: unknown) {
-            toast.error(t('toast.exportFailed'), { description: String(e) })
+            toast.error(t('toast.exportFailed'), { description: errorMessage(e) })
         }
     }
 
@@ -386,7 +540,7 @@ export function useCopyManager() {
             importFilePath.value = selected
             showImportDialog.value = true
         } catch (e: unknown) {
-            toast.error(t('toast.importAnalysisFailed'), { description: String(e) })
+            toast.error(t('toast.importAnalysisFailed'), { description: errorMessage(e) })
         }
     }
 
@@ -394,6 +548,7 @@ export function useCopyManager() {
         if (!importFilePath.value) return
 
         showImportDialog.value = false
+        const importPath = importFilePath.value
 
         try {
             const result = await invoke<ImportResultInfo>('execute_import', {
@@ -404,8 +559,23 @@ export function useCopyManager() {
             toast.success(t('toast.settingsImported'), {
                 description: t('toast.settingsImportedDesc', { imported: result.imported_count, skipped: result.skipped_count, backedUp: result.backed_up_count }),
             })
+            if (result.failed.length > 0) {
+                toast.error(t('toast.importFailed'), {
+                    description: t('toast.importPartialDesc', {
+                        count: result.failed.length,
+                        error: errorMessage(result.failed[0].error),
+                    }),
+                })
+            }
         } catch (e: unknown) {
-            toast.error(t('toast.importFailed'), { description: String(e) })
+            const queued = await offerToQueue(e, {
+                type: 'import',
+                import_path: importPath,
+                custom_eve_path: customEvePath.value,
+                overwrite_paths: overwritePaths,
+            })
+            if (queued) return
+            toast.error(t('toast.importFailed'), { description: errorMessage(e) })
         } finally {
             importAnalysis.value = null
             importFilePath.value = null
@@ -418,14 +588,23 @@ export function useCopyManager() {
         importFilePath.value = null
     }
 
-    async function setBracketsAlwaysShow(serverPath: string, enabled: boolean) {
+    async function setBracketsAlwaysShow(
+        serverPath: string,
+        enabled: boolean,
+        profilePath: string | null = null
+    ) {
         try {
-            await invoke('set_brackets_always_show', { serverPath, enabled })
+            await invoke('set_pref', {
+                serverPath,
+                profilePath,
+                name: 'bracketsAlwaysShowShipText',
+                value: enabled ? '1' : '0',
+            })
             toast.success(t('toast.settingUpdated'), {
                 description: t('toast.settingUpdatedDesc', { status: enabled ? t('common.enabled') : t('common.disabled') }),
             })
         } catch (e: unknown) {
-            toast.error(t('toast.updateSettingFailed'), { description: String(e) })
+            toast.error(t('toast.updateSettingFailed'), { description: errorMessage(e) })
         }
     }
 
@@ -34,7 +34,10 @@ export default {
     applyBackupDesc: 'Apply "{backup}" to {target}? This will overwrite current settings.',
     apply: 'Apply',
     exportSettings: 'Export EVE Settings',
-    importSettings: 'Import EVE Settings'
+    importSettings: 'Import EVE Settings',
+    clientRunning: 'EVE Is Running',
+    clientRunningDesc: '{message}. Run this automatically once the client has closed?',
+    runWhenClosed: 'Run when closed'
   },
   // Toast messages
   toast: {
@@ -55,6 +58,7 @@ export default {
     settingsCopied: 'Settings copied',
     settingsCopiedDesc: 'Successfully copied to {count} target(s)',
     copyFailed: 'Copy failed',
+    copyPartialDesc: 'Copied to {count} of {total} target(s): {error}',
     backupCreated: 'Backup created',
     backupCreatedDesc: '"{name}" has been saved',
     backupFailed: 'Backup failed',
@@ -74,15 +78,21 @@ export default {
     settingsImported: 'Settings imported',
     settingsImportedDesc: 'Imported {imported} file(s), skipped {skipped}, backed up {backedUp}',
     importFailed: 'Import failed',
+    importPartialDesc: '{count} file(s) could not be imported: {error}',
     settingUpdated: 'Setting updated',
     settingUpdatedDesc: 'Brackets always show {status}',
-    updateSettingFailed: 'Failed to update setting'
+    updateSettingFailed: 'Failed to update setting',
+    operationQueued: 'Waiting for EVE to close',
+    operationQueuedDesc: 'The change will be made once the client has exited',
+    queuedOperationDone: 'Queued change applied',
+    queuedOperationFailed: 'Queued change failed'
   },
   // Title Bar
   titleBar: {
     settings: 'Settings',
     backups: 'Backups',
-    toggleTheme: 'Toggle theme'
+    toggleTheme: 'Toggle theme',
+    clientRunning: 'EVE is running on this server. Its settings cannot be changed until the client exits.'
   },
   // Backup related
   backup: {
@@ -158,7 +168,9 @@ export default {
   extra: {
     title: 'Extra',
     alwaysShowBracketText: 'Always Show Bracket Text',
-    alwaysShowBracketTextDesc: 'Show ship labels on all brackets in space, not just selected targets. May impact performance with 200+ pilots on grid. Requires client restart.'
+    alwaysShowBracketTextDesc: 'Show ship labels on all brackets in space, not just selected targets. May impact performance with 200+ pilots on grid. Requires client restart.',
+    mixedProfiles: 'Enabled in some profiles only. Toggling applies to every profile.',
+    perProfile: 'Per profile'
   },
   // Empty state
   empty: {
@@ -34,7 +34,10 @@ export default {
     applyBackupDesc: '将"{backup}"应用到 {target}？这将覆盖当前设置。',
     apply: '应用',
     exportSettings: '导出EVE设置',
-    importSettings: '导入EVE设置'
+    importSettings: '导入EVE设置',
+    clientRunning: 'EVE 正在运行',
+    clientRunningDesc: '{message}。是否在客户端关闭后自动执行？',
+    runWhenClosed: '关闭后执行'
   },
   // 提示消息
   toast: {
@@ -55,6 +58,7 @@ export default {
     settingsCopied: '设置已复制',
     settingsCopiedDesc: '成功复制到 {count} 个目标',
     copyFailed: '复制失败',
+    copyPartialDesc: '已复制到 {count}/{total} 个目标：{error}',
     backupCreated: '备份已创建',
     backupCreatedDesc: '"{name}" 已保存',
     backupFailed: '备份失败',
@@ -74,15 +78,21 @@ export default {
     settingsImported: '设置已导入',
     settingsImportedDesc: '已导入 {imported} 个文件，跳过 {skipped} 个，备份 {backedUp} 个',
     importFailed: '导入失败',
+    importPartialDesc: '{count} 个文件无法导入：{error}',
     settingUpdated: '设置已更新',
     settingUpdatedDesc: '始终显示标签 {status}',
-    updateSettingFailed: '更新设置失败'
+    updateSettingFailed: '更新设置失败',
+    operationQueued: '等待 EVE 关闭',
+    operationQueuedDesc: '客户端退出后将自动应用更改',
+    queuedOperationDone: '排队的更改已应用',
+    queuedOperationFailed: '排队的更改失败'
   },
   // 标题栏
   titleBar: {
     settings: '设置',
     backups: '备份',
-    toggleTheme: '切换主题'
+    toggleTheme: '切换主题',
+    clientRunning: 'EVE 正在此服务器上运行。客户端退出前无法更改其设置。'
   },
   // 备份相关
   backup: {
@@ -158,7 +168,9 @@ export default {
   extra: {
     title: '额外',
     alwaysShowBracketText: '始终显示标签文本',
-    alwaysShowBracketTextDesc: '在游戏内所有图标上显示舰船名称，而不仅仅是选中的目标。在视野中有200+飞行员时可能会影响性能。重启EVE客户端后生效。'
+    alwaysShowBracketTextDesc: '在游戏内所有图标上显示舰船名称，而不仅仅是选中的目标。在视野中有200+飞行员时可能会影响性能。重启EVE客户端后生效。',
+    mixedProfiles: '仅部分配置中已启用。切换将应用到所有配置。',
+    perProfile: '按配置'
   },
   // 空状态
   empty: {
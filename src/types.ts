@@ -27,6 +27,7 @@ export interface SettingsEntry {
 export interface ProfileData {
     name: string
     path: string
+    brackets_always_show: boolean
     accounts: SettingsEntry[]
     characters: SettingsEntry[]
 }
@@ -38,7 +39,9 @@ export interface ServerInfo {
     color: string
     supports_esi: boolean
     brackets_always_show: boolean
+    brackets_mixed: boolean
     server_path: string
+    client_running: boolean
 }
 
 export interface ServerData {
@@ -56,6 +59,10 @@ export interface BackupEntry {
     original_name: string | null
     display_name: string
     relative_time: string
+    notes: string | null
+    tags: string[]
+    server: ServerId | null
+    profile: string | null
 }
 
 export interface AppData {
@@ -90,6 +97,395 @@ export interface ImportResultInfo {
     imported_count: number
     skipped_count: number
     backed_up_count: number
+    failed: TargetResult[]
+}
+
+export interface TargetResult {
+    path: string
+    error: AppError | null
+}
+
+export type SettingsValue =
+    | { type: 'none' }
+    | { type: 'bool'; value: boolean }
+    | { type: 'int'; value: number }
+    | { type: 'long'; value: number[] }
+    | { type: 'float'; value: number }
+    | { type: 'str'; value: string }
+    | { type: 'unicode'; value: string }
+    | { type: 'buffer'; value: number[] }
+    | { type: 'token'; value: string }
+    | { type: 'interned'; value: number }
+    | { type: 'tuple'; value: SettingsValue[] }
+    | { type: 'list'; value: SettingsValue[] }
+    | { type: 'dict'; value: [SettingsValue, SettingsValue][] }
+    | { type: 'object'; value: { class: SettingsValue; args: SettingsValue } }
+    | {
+          type: 'object_ex'
+          value: {
+              reduce: boolean
+              header: SettingsValue
+              list: SettingsValue[]
+              dict: [SettingsValue, SettingsValue][]
+          }
+      }
+    | { type: 'sub_struct'; value: SettingsValue }
+    | { type: 'sub_stream'; value: number[] }
+    | { type: 'checksummed'; value: { checksum: number; value: SettingsValue } }
+    | { type: 'pickle'; value: number[] }
+    | { type: 'shared'; value: [number, SettingsValue] }
+    | { type: 'ref'; value: [number, SettingsValue] }
+
+export interface SettingsTree {
+    path: string
+    size: number
+    root: SettingsValue
+}
+
+export interface SettingsKeyRule {
+    group: string
+    prefixes: string[]
+}
+
+export interface SettingsSection {
+    id: string
+    label: string
+    description: string
+    rules: SettingsKeyRule[]
+}
+
+export type ChangeKind = 'added' | 'removed' | 'changed'
+
+export interface KeyChange {
+    group: string
+    key: string
+    kind: ChangeKind
+    old_value: SettingsValue | null
+    new_value: SettingsValue | null
+}
+
+export interface SectionDiff {
+    section: string | null
+    label: string
+    changes: KeyChange[]
+}
+
+export interface SettingsDiff {
+    left_path: string
+    right_path: string
+    change_count: number
+    sections: SectionDiff[]
+}
+
+export interface ByteStats {
+    backup_size: number
+    target_size: number
+    backup_sha256: string
+    target_sha256: string
+    identical: boolean
+    differing_bytes: number
+    first_difference: number | null
+}
+
+export interface BackupDiff {
+    backup_path: string
+    target_path: string
+    bytes: ByteStats
+    change_count: number
+    sections: SectionDiff[]
+}
+
+export interface OverviewExportResult {
+    path: string
+    preset_count: number
+    tab_count: number
+}
+
+export interface ShortcutConflict {
+    keys: string
+    commands: string[]
+}
+
+export interface ShortcutReport {
+    path: string
+    id: string
+    server: ServerId
+    profile: string
+    bound_count: number
+    conflicts: ShortcutConflict[]
+    unbound: string[]
+    error: AppError | null
+}
+
+export interface ScreenSpec {
+    width: number
+    height: number
+    ui_scale: number
+}
+
+export interface WindowGeometry {
+    key: string
+    window: string
+    x: number
+    y: number
+    width: number
+    height: number
+}
+
+export interface WindowLayout {
+    path: string
+    desktop_width: number | null
+    desktop_height: number | null
+    windows: WindowGeometry[]
+}
+
+export type PrefType = 'bool' | 'int' | 'string'
+export type PrefRisk = 'safe' | 'caution'
+
+export interface PrefDef {
+    name: string
+    kind: PrefType
+    default: string
+    description: string
+    risk: PrefRisk
+}
+
+export interface ProfilePref {
+    profile: string
+    profile_path: string
+    value: string | null
+}
+
+export interface PrefSetting {
+    definition: PrefDef
+    value: string | null
+    mixed: boolean
+    profiles: ProfilePref[]
+}
+
+export interface PrefEntry {
+    key: string
+    value: string
+}
+
+export interface ServerPrefs {
+    server_path: string
+    prefs: PrefSetting[]
+    other: PrefEntry[]
+}
+
+export interface RetentionPolicy {
+    keep_last: number | null
+    keep_daily: number | null
+    keep_weekly: number | null
+    keep_monthly: number | null
+    max_total_size: number | null
+}
+
+export type PruneReason = 'retention' | 'max_total_size'
+
+export interface PrunedBackup {
+    backup: BackupEntry
+    size: number
+    reason: PruneReason
+}
+
+export interface PruneReport {
+    dry_run: boolean
+    kept_count: number
+    kept_bytes: number
+    pruned: PrunedBackup[]
+    freed_bytes: number
+    errors: string[]
+}
+
+export interface BackupSchedule {
+    enabled: boolean
+    interval_minutes: number | null
+    on_startup: boolean
+    paths: string[]
+    custom_eve_path: string | null
+}
+
+export interface ScheduledRunResult {
+    timestamp: number
+    created: BackupEntry[]
+    unchanged_count: number
+    pruned_count: number
+    errors: string[]
+}
+
+export interface FileSnapshot {
+    path: string
+    snapshot: string | null
+    backup: string | null
+    before_sha256: string | null
+    after_sha256: string | null
+    restored: boolean
+}
+
+export interface Operation {
+    id: string
+    kind: string
+    timestamp: number
+    inputs: Record<string, unknown>
+    files: FileSnapshot[]
+    undone_at: number | null
+}
+
+export interface UndoResult {
+    operation: Operation
+    files: TargetResult[]
+}
+
+export interface StoreMigrationResult {
+    migrated_count: number
+    already_migrated_count: number
+    blobs_written: number
+    bytes_saved: number
+    blobs_removed: number
+    errors: string[]
+}
+
+export interface BackupMetadata {
+    name: string
+    kind: SettingsKind
+    original_id: string
+    timestamp: number
+    notes: string | null
+    tags: string[]
+    source_path: string | null
+    server: ServerId | null
+    profile: string | null
+    character_name: string | null
+    sha256: string | null
+    app_version: string | null
+}
+
+export interface BackupQuery {
+    tags?: string[]
+    kind?: SettingsKind | null
+    server?: ServerId | null
+    profile?: string | null
+    original_id?: string | null
+    from?: number | null
+    to?: number | null
+    text?: string | null
+}
+
+export type RestoreMtime = 'now' | 'backup' | 'keep'
+
+export interface RestoreResult {
+    target_path: string
+    kind: SettingsKind
+    target_id: string
+    sha256: string
+    verified: boolean
+    safety_backup: BackupEntry | null
+}
+
+export interface BackupMoveResult {
+    moved_count: number
+    blobs_removed: number
+    errors: string[]
+}
+
+export type BackupProblem =
+    | 'unreadable'
+    | 'hash_mismatch'
+    | 'undecodable'
+    | 'invalid_metadata'
+    | 'filename_mismatch'
+    | 'orphaned'
+    | 'duplicate'
+
+export interface BackupIssue {
+    backup: BackupEntry
+    problem: BackupProblem
+    detail: string
+}
+
+export interface VerifyReport {
+    checked_count: number
+    ok_count: number
+    issues: BackupIssue[]
+    quarantined: string[]
+    errors: string[]
+}
+
+export type ErrorCode =
+    | 'not_found'
+    | 'permission_denied'
+    | 'file_locked'
+    | 'already_exists'
+    | 'invalid_input'
+    | 'invalid_data'
+    | 'unsupported'
+    | 'conflict'
+    | 'client_running'
+    | 'network'
+    | 'io'
+    | 'other'
+
+export interface AppError {
+    code: ErrorCode
+    message: string
+    path: string | null
+    retryable: boolean
+}
+
+export interface RunningClient {
+    pid: number
+    server: ServerId
+}
+
+export type PendingOperation =
+    | { type: 'copy'; source_path: string; target_paths: string[] }
+    | {
+          type: 'import'
+          import_path: string
+          custom_eve_path: string | null
+          overwrite_paths: string[]
+      }
+    | {
+          type: 'sections'
+          source_path: string
+          target_paths: string[]
+          sections: string[]
+      }
+    | { type: 'overview'; yaml_path: string; target_paths: string[] }
+    | { type: 'shortcuts'; import_path: string; target_paths: string[] }
+    | {
+          type: 'window_layout'
+          source_path: string
+          target_paths: string[]
+          source_screen: ScreenSpec | null
+          target_screen: ScreenSpec
+      }
+
+export interface QueuedOperation {
+    id: number
+    operation: PendingOperation
+    queued_at: number
+}
+
+export interface QueuedOperationResult {
+    id: number
+    error: AppError | null
+}
+
+export type FileChange = 'added' | 'modified' | 'removed'
+
+export interface SettingsEntryEvent {
+    change: FileChange
+    path: string
+    entry: SettingsEntry | null
+}
+
+export interface BackupEvent {
+    change: FileChange
+    path: string
+    backup: BackupEntry | null
 }
 
 export type SourceItem = SettingsEntry | BackupEntry
@@ -98,6 +494,19 @@ export function isBackup(item: SourceItem): item is BackupEntry {
     return 'original_id' in item
 }
 
+export function isAppError(error: unknown): error is AppError {
+    return (
+        typeof error === 'object' &&
+        error !== null &&
+        'code' in error &&
+        'message' in error
+    )
+}
+
+export function errorMessage(error: unknown): string {
+    return isAppError(error) ? error.message : String(error)
+}
+
 export function getServerColor(serverId: ServerId): string {
     const colors: Record<ServerId, string> = {
         tranquility: 'hsl(160, 100%, 40%)',